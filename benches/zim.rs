@@ -0,0 +1,59 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use zim::{Compression, Zim};
+
+const FIXTURE: &str = "fixtures/wikipedia_ab_all_2017-03.zim";
+
+fn open() -> Zim {
+    Zim::new(FIXTURE).expect("failed to parse fixture")
+}
+
+/// The first LZMA2 compressed cluster, which holds the articles in the fixture.
+fn lzma2_cluster(zim: &Zim) -> u32 {
+    zim.iterate_clusters()
+        .find(|(_, cluster)| match cluster {
+            Ok(cluster) => cluster.compression() == Compression::LZMA2,
+            Err(_) => false,
+        })
+        .map(|(idx, _)| idx)
+        .expect("the fixture has no LZMA2 cluster")
+}
+
+fn bench_open(c: &mut Criterion) {
+    c.bench_function("Zim::new", |b| b.iter(open));
+}
+
+fn bench_entries(c: &mut Criterion) {
+    let zim = open();
+    let middle = zim.article_count() as u32 / 2;
+
+    c.bench_function("get_by_url_index", |b| {
+        b.iter(|| zim.get_by_url_index(black_box(middle)).unwrap())
+    });
+    c.bench_function("iterate_by_urls", |b| {
+        b.iter(|| zim.iterate_by_urls().count())
+    });
+}
+
+fn bench_clusters(c: &mut Criterion) {
+    let zim = open();
+    let idx = lzma2_cluster(&zim);
+
+    // a new cluster every iteration, as decompression is only done once per cluster
+    c.bench_function("Cluster::decompress", |b| {
+        b.iter(|| {
+            zim.get_cluster(black_box(idx))
+                .unwrap()
+                .decompress()
+                .unwrap()
+        })
+    });
+
+    let cluster = zim.get_cluster(idx).unwrap();
+    cluster.decompress().unwrap();
+    c.bench_function("Cluster::get_blob", |b| {
+        b.iter(|| cluster.get_blob(black_box(0)).unwrap().len())
+    });
+}
+
+criterion_group!(benches, bench_open, bench_entries, bench_clusters);
+criterion_main!(benches);
@@ -1,15 +1,39 @@
-use std::collections::HashMap;
-use std::error::Error;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
 use std::io::{BufWriter, Write};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 use clap::{App, Arg};
+use glob::Pattern;
 use indicatif::{ProgressBar, ProgressStyle};
 use rayon::prelude::*;
 use stopwatch::Stopwatch;
 use zim::{Cluster, DirectoryEntry, MimeType, Namespace, Target, Zim};
 
+/// Number of entries written and the time spent on them, per MIME type.
+type MimeTimes = Mutex<HashMap<String, (u64, Duration)>>;
+
+/// Number of entries written and skipped because they were already extracted, with `--resume`.
+#[derive(Default)]
+struct Resume {
+    written: AtomicU64,
+    skipped: AtomicU64,
+}
+
+impl Resume {
+    fn message(&self) -> String {
+        format!(
+            "Writing entries to disk ({} written, {} skipped as already extracted)",
+            self.written.load(Ordering::Relaxed),
+            self.skipped.load(Ordering::Relaxed)
+        )
+    }
+}
+
 fn main() {
     let matches = App::new("zimextractor")
         .version("0.1")
@@ -33,6 +57,49 @@ fn main() {
                 .help("Write files to disk, instead of using hard links")
                 .takes_value(false),
         )
+        .arg(
+            Arg::with_name("time-by-mime")
+                .long("time-by-mime")
+                .help("Report the time spent writing entries, per MIME type")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("resume")
+                .long("resume")
+                .help("Skip entries which already exist in the output with the right size")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("dry-run")
+                .long("dry-run")
+                .help("Print what would be extracted, without writing anything")
+                .takes_value(false)
+                .conflicts_with("resume"),
+        )
+        .arg(
+            Arg::with_name("namespace")
+                .long("namespace")
+                .help("Only extract entries in this namespace, e.g. A (repeatable)")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("exclude-namespace")
+                .long("exclude-namespace")
+                .help("Skip entries in this namespace, e.g. X (repeatable)")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("mime-filter")
+                .long("mime-filter")
+                .help("Only extract entries whose MIME type matches, e.g. \"image/*\" (repeatable)")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
         .arg(
             Arg::with_name("INPUT")
                 .help("Set the zim file to extract")
@@ -43,6 +110,30 @@ fn main() {
 
     let skip_link = matches.is_present("skip-link");
     let flatten_link = matches.is_present("flatten-link");
+    let dry_run = matches.is_present("dry-run");
+    let mime_times = if matches.is_present("time-by-mime") {
+        Some(MimeTimes::default())
+    } else {
+        None
+    };
+    let resume = if matches.is_present("resume") {
+        Some(Resume::default())
+    } else {
+        None
+    };
+    let namespaces = parse_namespaces(matches.values_of("namespace"));
+    let excluded = parse_namespaces(matches.values_of("exclude-namespace"));
+    let mime_filter: Vec<Pattern> = matches
+        .values_of("mime-filter")
+        .into_iter()
+        .flatten()
+        .map(|filter| {
+            Pattern::new(filter).unwrap_or_else(|err| {
+                eprintln!("invalid mime filter {:?}: {}", filter, err);
+                std::process::exit(1);
+            })
+        })
+        .collect();
     let out = matches.value_of("out").unwrap_or("out");
     let root_output = Path::new(out);
 
@@ -71,40 +162,77 @@ fn main() {
         .progress_chars("#>-");
     pb.set_style(style);
 
-    ensure_dir(root_output);
+    if !dry_run {
+        ensure_dir(root_output);
+    }
+
+    let entries: Vec<_> = zim_file
+        .par_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| namespaces.is_empty() || namespaces.contains(&entry.namespace))
+        .filter(|entry| !excluded.contains(&entry.namespace))
+        .filter(|entry| mime_filter.is_empty() || matches_mime(&zim_file, entry, &mime_filter))
+        .collect();
+    pb.set_length(entries.len() as u64);
+
+    // group the entries by the cluster and blob their content is stored in
+    let mut cluster_entries: HashMap<u32, HashMap<u32, Vec<&DirectoryEntry>>> = HashMap::new();
+    for entry in &entries {
+        if let Some(Target::Cluster(cluster_idx, blob_idx)) = entry.target {
+            cluster_entries
+                .entry(cluster_idx)
+                .or_default()
+                .entry(blob_idx)
+                .or_default()
+                .push(entry);
+        }
+    }
 
-    // map between cluster and directory entry
-    let mut cluster_map = HashMap::new();
+    // only clusters with entries left after filtering are read
+    let mut cluster_indices: Vec<u32> = cluster_entries.keys().cloned().collect();
+    cluster_indices.sort_unstable();
 
-    for i in 0..zim_file.header.cluster_count {
-        let cluster = zim_file.get_cluster(i).expect("failed to retrieve cluster");
-        cluster_map.insert(i, cluster);
+    if dry_run {
+        pb.set_message("Decompressing clusters");
+        let (cluster_map, failures) = zim_file.decompress_clusters(&cluster_indices);
+        for (cluster_idx, err) in &failures {
+            eprintln!("broken cluster {}: {}", cluster_idx, err);
+        }
+        pb.finish_and_clear();
+        print_dry_run(root_output, &entries, &cluster_map);
+        return;
     }
 
-    let entries: Vec<_> = zim_file.iterate_by_urls().collect();
-    pb.set_message("Writing entries to disk");
-    entries
-        .par_iter()
-        .filter(|entry| {
-            if let Some(Target::Cluster(_, _)) = entry.target.as_ref() {
-                return true;
+    // clusters are decompressed while writing, and freed once written, so only the clusters
+    // currently being written are held in memory
+    let mut cluster_map = HashMap::with_capacity(cluster_indices.len());
+    for cluster_idx in cluster_indices {
+        match zim_file.get_cluster(cluster_idx) {
+            Ok(cluster) => {
+                cluster_map.insert(cluster_idx, cluster);
             }
-            false
-        })
-        .for_each(|entry| {
-            process_file(&root_output, &cluster_map, entry, &pb);
-        });
+            Err(err) => eprintln!("broken cluster {}: {}", cluster_idx, err),
+        }
+    }
+
+    pb.set_message("Writing entries to disk");
+    cluster_entries.par_iter().for_each(|(cluster_idx, blobs)| {
+        process_cluster(
+            &root_output,
+            &cluster_map,
+            *cluster_idx,
+            blobs,
+            mime_times.as_ref(),
+            resume.as_ref(),
+            &pb,
+        );
+    });
 
     if !skip_link {
         pb.set_message("Generating links");
         entries
             .par_iter()
-            .filter(|entry| {
-                if let Some(Target::Redirect(_)) = entry.target.as_ref() {
-                    return true;
-                }
-                false
-            })
+            .filter(|entry| entry.target.as_ref().is_some_and(Target::is_redirect))
             .for_each(|entry| {
                 process_link(&zim_file, &root_output, entry, skip_link, flatten_link, &pb);
             });
@@ -114,6 +242,147 @@ fn main() {
         "Extraction done in {}s",
         sw.elapsed_ms() as f64 / 1000.
     ));
+
+    if let Some(resume) = resume {
+        println!(
+            "{} entries written, {} skipped as already extracted",
+            resume.written.into_inner(),
+            resume.skipped.into_inner()
+        );
+    }
+
+    if let Some(mime_times) = mime_times {
+        print_mime_times(mime_times.into_inner().unwrap());
+    }
+}
+
+/// Prints the path and size of every entry which would be written, followed by a summary.
+fn print_dry_run(
+    root_output: &Path,
+    entries: &[DirectoryEntry],
+    cluster_map: &HashMap<u32, Cluster>,
+) {
+    let mut namespaces: BTreeMap<Namespace, (u64, u64)> = BTreeMap::new();
+    let mut redirects = 0;
+    let mut failures = 0;
+
+    for entry in entries {
+        let (cluster_idx, blob_idx) = match entry.target {
+            Some(Target::Cluster(cluster_idx, blob_idx)) => (cluster_idx, blob_idx),
+            Some(Target::Redirect(_)) => {
+                redirects += 1;
+                continue;
+            }
+            _ => continue,
+        };
+
+        let dst = make_path(root_output, entry);
+        let size = match cluster_map.get(&cluster_idx) {
+            Some(cluster) => cluster.map_blob(blob_idx, |blob| blob.len() as u64),
+            None => {
+                // reported as a broken cluster
+                failures += 1;
+                continue;
+            }
+        };
+        match size {
+            Ok(size) => {
+                println!("WOULD WRITE {} ({})", dst.display(), size);
+                let (count, bytes) = namespaces.entry(entry.namespace).or_default();
+                *count += 1;
+                *bytes += size;
+            }
+            Err(err) => {
+                eprintln!("would skip {}: {}", dst.display(), err);
+                failures += 1;
+            }
+        }
+    }
+
+    let articles: u64 = namespaces.values().map(|&(count, _)| count).sum();
+    let bytes: u64 = namespaces.values().map(|&(_, bytes)| bytes).sum();
+    println!(
+        "
+{} articles, {} bytes",
+        articles, bytes
+    );
+    println!("{} redirects", redirects);
+    if failures > 0 {
+        println!("{} entries could not be read", failures);
+    }
+    for (namespace, (count, bytes)) in namespaces {
+        println!("{}: {} articles, {} bytes", namespace, count, bytes);
+    }
+}
+
+/// Whether the MIME type of `entry`, or of its target for redirects, matches one of `patterns`.
+fn matches_mime(zim_file: &Zim, entry: &DirectoryEntry, patterns: &[Pattern]) -> bool {
+    let target;
+    let mime = match (&entry.mime_type, &entry.target) {
+        (MimeType::Type(mime), _) => mime,
+        (_, Some(Target::Redirect(idx))) => {
+            target = match zim_file.get_by_url_index(*idx) {
+                Ok(target) => target,
+                Err(_) => return false,
+            };
+            match target.mime_type {
+                MimeType::Type(ref mime) => mime,
+                _ => return false,
+            }
+        }
+        _ => return false,
+    };
+
+    patterns.iter().any(|pattern| pattern.matches(mime))
+}
+
+/// Parses the values of a namespace flag, exiting on unknown namespaces.
+fn parse_namespaces(values: Option<clap::Values>) -> Vec<Namespace> {
+    values
+        .into_iter()
+        .flatten()
+        .map(|value| {
+            value.parse().unwrap_or_else(|_| {
+                let known: Vec<_> = Namespace::ALL.iter().map(Namespace::to_string).collect();
+                eprintln!(
+                    "unknown namespace {:?}, expected one of: {}",
+                    value,
+                    known.join(" ")
+                );
+                std::process::exit(1);
+            })
+        })
+        .collect()
+}
+
+fn print_mime_times(mime_times: HashMap<String, (u64, Duration)>) {
+    let mut mime_times: Vec<_> = mime_times.into_iter().collect();
+    // slowest first
+    mime_times.sort_by_key(|&(_, (_, time))| Reverse(time));
+
+    println!("\nTime by MIME type:");
+    for (mime, (count, time)) in mime_times {
+        let secs = time.as_secs_f64();
+        println!(
+            "{}: {} articles in {:.1}s ({:.1}ms/article)",
+            mime,
+            count,
+            secs,
+            secs * 1000. / count as f64
+        );
+    }
+}
+
+fn record_time(mime_times: &MimeTimes, entry: &DirectoryEntry, time: Duration) {
+    let mime = match entry.mime_type {
+        MimeType::Type(ref mime) => mime.as_str(),
+        _ => "other",
+    };
+
+    let mut mime_times = mime_times.lock().unwrap();
+    let (count, total) = mime_times.entry(mime.to_string()).or_default();
+    *count += 1;
+    *total += time;
 }
 
 fn safe_write<T: AsRef<[u8]>>(path: &Path, data: T, count: usize) {
@@ -137,11 +406,7 @@ fn safe_write<T: AsRef<[u8]>>(path: &Path, data: T, count: usize) {
             let mut writer = BufWriter::new(&file);
 
             if let Err(why) = writer.write_all(data.as_ref()) {
-                eprintln!(
-                    "skipping: couldn't write to {}: {}",
-                    display,
-                    why.description()
-                );
+                eprintln!("skipping: couldn't write to {}: {}", display, why);
             }
         }
     }
@@ -157,33 +422,108 @@ fn ensure_dir(path: &Path) {
         .unwrap_or_else(|e| ignore_exists_err(e, &format!("create: {}", path.display())));
 }
 
-fn process_file<'a>(
+fn process_cluster<'a>(
     root_output: &Path,
     cluster_map: &'a HashMap<u32, Cluster<'a>>,
-    entry: &DirectoryEntry,
+    cluster_idx: u32,
+    blobs: &HashMap<u32, Vec<&DirectoryEntry>>,
+    mime_times: Option<&MimeTimes>,
+    resume: Option<&Resume>,
     pb: &ProgressBar,
 ) {
-    let dst = make_path(root_output, entry.namespace, &entry.url, &entry.mime_type);
-    match entry.target.as_ref() {
-        Some(Target::Cluster(cluster_index, blob_idx)) => {
-            let cluster = cluster_map.get(cluster_index).expect("missing cluster");
+    let cluster = match cluster_map.get(&cluster_idx) {
+        Some(cluster) => cluster,
+        None => {
+            for entry in blobs.values().flatten() {
+                eprintln!("skipping {}: cluster {} is missing", entry.url, cluster_idx);
+            }
+            pb.inc(blobs.values().map(|entries| entries.len() as u64).sum());
+            return;
+        }
+    };
 
-            match cluster.get_blob(*blob_idx) {
-                Ok(blob) => {
-                    safe_write(&dst, blob, 1);
-                }
-                Err(err) => {
-                    eprintln!("skipping invalid blob: {}: {}", dst.display(), err);
+    // decompresses the cluster, so a broken one is reported once for all of its entries
+    let blob_count = match cluster.blob_count() {
+        Ok(blob_count) => blob_count,
+        Err(err) => {
+            eprintln!("broken cluster {}: {}", cluster_idx, err);
+            for entry in blobs.values().flatten() {
+                eprintln!("skipping {}: cluster {} is broken", entry.url, cluster_idx);
+            }
+            pb.inc(blobs.values().map(|entries| entries.len() as u64).sum());
+            cluster.drop_decompressed();
+            return;
+        }
+    };
+
+    for (blob_idx, blob) in (0..).zip(cluster.iter_blobs()) {
+        let entries = match blobs.get(&blob_idx) {
+            Some(entries) => entries,
+            None => continue,
+        };
+        match blob {
+            Ok(blob) => write_entries(root_output, entries, &blob, mime_times, resume, pb),
+            Err(err) => {
+                for entry in entries {
+                    eprintln!("skipping invalid blob: {}: {}", entry.url, err);
                 }
+                pb.inc(entries.len() as u64);
             }
-            pb.inc(1);
         }
-        Some(_) => unreachable!("filtered out earlier"),
-        None => {
-            eprintln!("skipping missing target {} {:?}", dst.display(), entry);
+    }
+
+    for (blob_idx, entries) in blobs.iter().filter(|(idx, _)| **idx >= blob_count) {
+        for entry in entries {
+            eprintln!(
+                "skipping {}: blob {} does not exist, cluster {} has {} blobs",
+                entry.url, blob_idx, cluster_idx, blob_count
+            );
         }
+        pb.inc(entries.len() as u64);
     }
+
+    // the blobs have been written, and the iterator no longer locks the cluster
+    cluster.drop_decompressed();
 }
+
+fn write_entries(
+    root_output: &Path,
+    entries: &[&DirectoryEntry],
+    blob: &[u8],
+    mime_times: Option<&MimeTimes>,
+    resume: Option<&Resume>,
+    pb: &ProgressBar,
+) {
+    for entry in entries {
+        let start = Instant::now();
+        let dst = make_path(root_output, entry);
+
+        let skip = resume.is_some() && is_extracted(&dst, blob);
+        if let Some(resume) = resume {
+            let counter = if skip {
+                &resume.skipped
+            } else {
+                &resume.written
+            };
+            counter.fetch_add(1, Ordering::Relaxed);
+            pb.set_message(&resume.message());
+        }
+
+        if !skip {
+            safe_write(&dst, blob, 1);
+            if let Some(mime_times) = mime_times {
+                record_time(mime_times, entry, start.elapsed());
+            }
+        }
+        pb.inc(1);
+    }
+}
+
+/// Whether `path` is a file of the same size as `blob`, e.g. from an interrupted extraction.
+fn is_extracted(path: &Path, blob: &[u8]) -> bool {
+    std::fs::metadata(path).is_ok_and(|meta| meta.is_file() && meta.len() == blob.len() as u64)
+}
+
 fn process_link(
     zim_file: &Zim,
     root_output: &Path,
@@ -192,7 +532,7 @@ fn process_link(
     flatten_link: bool,
     pb: &ProgressBar,
 ) {
-    let dst = make_path(root_output, entry.namespace, &entry.url, &entry.mime_type);
+    let dst = make_path(root_output, entry);
 
     if entry.target.is_none() {
         eprintln!("skipping missing target {:?} {:?}", dst, entry);
@@ -204,12 +544,15 @@ fn process_link(
             if !skip_link && !dst.exists() {
                 pb.inc_length(1);
 
-                let entry = {
-                    zim_file
-                        .get_by_url_index(*redir)
-                        .expect("failed to get_by_url_index")
+                let entry = match zim_file.get_by_url_index(*redir) {
+                    Ok(entry) => entry,
+                    Err(err) => {
+                        eprintln!("skipping redirect {:?}: {}", dst, err);
+                        pb.inc(1);
+                        return;
+                    }
                 };
-                let src = make_path(root_output, entry.namespace, &entry.url, &entry.mime_type);
+                let src = make_path(root_output, &entry);
                 make_link(src, dst, flatten_link);
                 pb.inc(1);
             }
@@ -262,42 +605,46 @@ fn ignore_exists_err<T: AsRef<str>>(e: std::io::Error, msg: T) {
     }
 }
 
-fn make_path(root: &Path, namespace: Namespace, url: &str, mime_type: &MimeType) -> PathBuf {
-    let mut s = String::new();
-    s.push(namespace as u8 as char);
-    let mut path = if url.starts_with("/") {
-        // make absolute urls relative to the output folder
-        let url = url.replacen("/", "", 1);
-        root.join(&s).join(url)
-    } else {
-        root.join(&s).join(url)
-    };
+fn make_path(root: &Path, entry: &DirectoryEntry) -> PathBuf {
+    root.join(entry.as_path())
+}
 
-    if let MimeType::Type(typ) = mime_type {
-        let extension = match typ.as_str() {
-            "text/html" => Some("html"),
-            "image/jpeg" => Some("jpg"),
-            "image/png" => Some("png"),
-            "image/gif" => Some("gif"),
-            "image/svg+xml" => Some("svg"),
-            "application/javascript" => Some("js"),
-            "text/css" => Some("css"),
-            "text/plain" => Some("txt"),
-            _ => None,
+#[test]
+fn test_make_path_extensions() {
+    use zim::Namespace;
+
+    let root = Path::new("out");
+    let cases = [
+        ("application/xhtml+xml", "A/page.xhtml"),
+        ("image/webp", "I/image.webp"),
+        ("application/pdf", "A/page.pdf"),
+        ("video/mp4", "A/page.mp4"),
+        ("audio/mpeg", "A/page.mp3"),
+        ("application/json", "A/page.json"),
+    ];
+
+    for &(mime, expected) in &cases {
+        let namespace = if mime.starts_with("image/") {
+            Namespace::ImagesFile
+        } else {
+            Namespace::Articles
+        };
+        let url = if mime.starts_with("image/") {
+            "image"
+        } else {
+            "page"
         };
-        if let Some(extension) = extension {
-            if path.extension().is_none()
-                || !path
-                    .extension()
-                    .unwrap()
-                    .to_str()
-                    .unwrap_or_default()
-                    .starts_with(extension)
-            {
-                path.set_extension(extension);
-            }
-        }
-    }
 
-    path
+        let entry = DirectoryEntry {
+            mime_type: MimeType::Type(mime.into()),
+            namespace,
+            revision: None,
+            url: url.into(),
+            title: String::new(),
+            target: None,
+            url_index: None,
+        };
+        let path = make_path(root, &entry);
+        assert_eq!(path, root.join(expected), "{}", mime);
+    }
 }
@@ -1,22 +1,25 @@
 extern crate clap;
 extern crate pbr;
 extern crate stopwatch;
+extern crate ureq;
 extern crate zim;
 
 use clap::{App, Arg};
 use pbr::MultiBar;
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::Write;
-use std::path::{Path, PathBuf};
+use std::path::Path;
 use std::thread;
+use std::time::Duration;
 use stopwatch::Stopwatch;
 use zim::{Target, Zim};
 
+/// How often a link is attempted when the IPFS API answers with a server error.
+const MAX_ATTEMPTS: usize = 3;
+
 fn main() {
     let matches = App::new("zim-linkr")
         .version("0.1")
-        .about("Link ipfs files via 'ipfs files' api")
+        .about("Link ipfs files via the 'ipfs files' HTTP api")
         .arg(
             Arg::with_name("ROOT")
                 .help("Root of the extracted content in the 'ipfs files' api")
@@ -29,12 +32,26 @@ fn main() {
                 .required(true)
                 .index(2),
         )
+        .arg(
+            Arg::with_name("ipfs-api")
+                .long("ipfs-api")
+                .help("Address of the IPFS HTTP api")
+                .takes_value(true)
+                .default_value("http://127.0.0.1:5001"),
+        )
+        .arg(
+            Arg::with_name("dry-run")
+                .long("dry-run")
+                .help("Only print what would be linked, without calling the IPFS api"),
+        )
         .get_matches();
 
     let root = matches.value_of("ROOT").unwrap();
     let root_output = Path::new(root);
 
     let input = matches.value_of("INPUT").unwrap();
+    let api = matches.value_of("ipfs-api").unwrap().trim_end_matches('/');
+    let dry_run = matches.is_present("dry-run");
     let mut mb = MultiBar::new();
 
     mb.println(&format!("Linking files using {} into {}:", input, root));
@@ -49,6 +66,7 @@ fn main() {
 
     let mut p1 = mb.create_bar(zim.header.cluster_count as u64);
     let mut p3 = mb.create_bar(zim.header.cluster_count as u64);
+    let mut p4 = mb.create_bar(0);
 
     thread::spawn(move || {
         mb.listen();
@@ -67,9 +85,12 @@ fn main() {
     p1.finish_print("Created cluster map");
 
     p3.show_message = true;
-    p3.message("Linking redirects :");
+    p3.message("Collecting redirects :");
 
     let mut ops = Vec::new();
+    // whether the content the redirects point to is part of the zim file
+    let mut sources_found = 0;
+    let mut sources_missing = 0;
 
     // link all redirects
     for entry in zim.iterate_by_urls() {
@@ -86,19 +107,71 @@ fn main() {
             let dst = root_output.join(&s).join(&entry.url);
 
             if src != dst {
-                ops.push(format!(
-                    "ipfs files cp {} {}",
-                    src.to_str().unwrap(),
-                    dst.to_str().unwrap()
+                if let Some(Target::Cluster(..)) = redir.target {
+                    sources_found += 1;
+                } else {
+                    sources_missing += 1;
+                }
+                ops.push((
+                    src.to_str().unwrap().to_string(),
+                    dst.to_str().unwrap().to_string(),
                 ));
             }
         }
         p3.inc();
     }
 
-    let mut f = File::create(PathBuf::from("link.txt")).unwrap();
-    f.write_all(ops.join("\n").as_bytes()).unwrap();
-    f.sync_data().unwrap();
+    p3.finish_print(&format!("Collected {} redirects", ops.len()));
 
-    p3.finish_print(&format!("Linking done in {}ms", sw.elapsed_ms()));
+    if dry_run {
+        p4.finish_print(&format!(
+            "Dry run: {} links would be created, {} sources exist, {} are missing",
+            ops.len(),
+            sources_found,
+            sources_missing
+        ));
+        return;
+    }
+
+    p4.total = ops.len() as u64;
+    p4.show_message = true;
+    p4.message("Linking redirects :");
+
+    let url = format!("{}/api/v0/files/cp", api);
+    let mut succeeded = 0;
+    let mut failed = 0;
+
+    for (src, dst) in &ops {
+        match files_cp(&url, src, dst) {
+            Ok(()) => succeeded += 1,
+            Err(err) => {
+                failed += 1;
+                p4.message(&format!("failed to link {} -> {}: {} :", src, dst, err));
+            }
+        }
+        p4.inc();
+    }
+
+    p4.finish_print(&format!(
+        "Linking done in {}ms: {} succeeded, {} failed",
+        sw.elapsed_ms(),
+        succeeded,
+        failed
+    ));
+}
+
+/// Copies `src` to `dst` through the `files/cp` endpoint, retrying on server errors.
+fn files_cp(url: &str, src: &str, dst: &str) -> Result<(), Box<ureq::Error>> {
+    let mut attempt = 1;
+
+    loop {
+        match ureq::post(url).query("arg", src).query("arg", dst).call() {
+            Ok(_) => return Ok(()),
+            Err(ureq::Error::Status(code, _)) if code >= 500 && attempt < MAX_ATTEMPTS => {
+                thread::sleep(Duration::from_millis(100 * attempt as u64));
+                attempt += 1;
+            }
+            Err(err) => return Err(Box::new(err)),
+        }
+    }
 }
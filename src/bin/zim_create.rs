@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fs::{self, File};
+use std::io::{self, BufWriter};
+use std::path::{Path, PathBuf};
+use std::process;
+
+use clap::{App, Arg};
+use zim::{Compression, MimeType, Namespace, ZimWriterBuilder};
+
+/// Directory of the input whose files are added as metadata.
+const METADATA_DIR: &str = "M";
+
+fn main() {
+    let matches = App::new("zim_create")
+        .version("0.1")
+        .about("Create a zim file from a directory")
+        .arg(
+            Arg::with_name("input")
+                .long("input")
+                .help("Directory to add, file paths relative to it are used as URLs")
+                .takes_value(true)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("output")
+                .long("output")
+                .help("The zim file to write")
+                .takes_value(true)
+                .required(true),
+        )
+        .arg(
+            Arg::with_name("compression")
+                .long("compression")
+                .help("Compression of the clusters")
+                .takes_value(true)
+                .possible_values(&["none", "lzma2", "bzip2"])
+                .default_value("lzma2"),
+        )
+        .arg(
+            Arg::with_name("main-page")
+                .long("main-page")
+                .help("URL of the main page, relative to the input directory")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("metadata")
+                .long("metadata")
+                .help("Adds a metadata entry, e.g. Title=Example")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("namespace-map")
+                .long("namespace-map")
+                .help("Adds files with an extension to a namespace, e.g. png=I. The default is A")
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .get_matches();
+
+    let input = Path::new(matches.value_of("input").unwrap());
+    let output = matches.value_of("output").unwrap();
+    let compression = match matches.value_of("compression").unwrap() {
+        "none" => Compression::None,
+        "bzip2" => Compression::Bzip2,
+        _ => Compression::LZMA2,
+    };
+
+    let mut namespace_map = HashMap::new();
+    for mapping in matches.values_of("namespace-map").into_iter().flatten() {
+        let (extension, namespace) = split_pair(mapping, "namespace mapping");
+        let namespace = namespace
+            .parse::<Namespace>()
+            .unwrap_or_else(|err| fail(format!("invalid namespace mapping {}", mapping), err));
+        namespace_map.insert(extension.trim_start_matches('.').to_lowercase(), namespace);
+    }
+    let namespace_for = |url: &str| {
+        namespace_map
+            .get(&extension(url).to_lowercase())
+            .cloned()
+            .unwrap_or(Namespace::Articles)
+    };
+
+    let mut files = Vec::new();
+    collect_files(input, &mut files)
+        .unwrap_or_else(|err| fail(format!("failed to read {}", input.display()), err));
+
+    let mut writer = ZimWriterBuilder::new().compression(compression).build();
+    for path in files {
+        let url = match url_for(input, &path) {
+            Some(url) => url,
+            None => {
+                eprintln!("skipping {}: the path is not valid utf-8", path.display());
+                continue;
+            }
+        };
+        let data =
+            fs::read(&path).unwrap_or_else(|err| fail(format!("failed to read {}", url), err));
+
+        let added = match url
+            .strip_prefix(METADATA_DIR)
+            .and_then(|url| url.strip_prefix('/'))
+        {
+            Some(name) => writer.add_article(Namespace::Metadata, name, "", "text/plain", &data),
+            None => {
+                let mime = match MimeType::from_extension(extension(&url)) {
+                    Some(MimeType::Type(mime)) => mime,
+                    _ => "application/octet-stream".into(),
+                };
+                writer.add_article(namespace_for(&url), &url, "", &mime, &data)
+            }
+        };
+        added.unwrap_or_else(|err| fail(format!("failed to add {}", url), err));
+    }
+
+    for metadata in matches.values_of("metadata").into_iter().flatten() {
+        let (key, value) = split_pair(metadata, "metadata");
+        writer
+            .add_article(Namespace::Metadata, key, "", "text/plain", value.as_bytes())
+            .unwrap_or_else(|err| fail(format!("failed to add metadata {}", key), err));
+    }
+
+    if let Some(main_page) = matches.value_of("main-page") {
+        writer.set_main_page(namespace_for(main_page), main_page);
+    }
+
+    let count = writer.len();
+    let file = File::create(output)
+        .unwrap_or_else(|err| fail(format!("failed to create {}", output), err));
+    writer
+        .finalize(BufWriter::new(file))
+        .unwrap_or_else(|err| fail(format!("failed to write {}", output), err));
+
+    println!("Wrote {} entries to {}", count, output);
+}
+
+/// Adds all files below `dir` to `files`, sorted by path.
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    let mut entries = fs::read_dir(dir)?.collect::<io::Result<Vec<_>>>()?;
+    entries.sort_by_key(|entry| entry.path());
+
+    for entry in entries {
+        let path = entry.path();
+        if entry.file_type()?.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+
+    Ok(())
+}
+
+/// The URL of a file: its path relative to `root`, separated by slashes on all platforms.
+fn url_for(root: &Path, path: &Path) -> Option<String> {
+    let components = path
+        .strip_prefix(root)
+        .ok()?
+        .iter()
+        .map(|component| component.to_str())
+        .collect::<Option<Vec<_>>>()?;
+    Some(components.join("/"))
+}
+
+fn extension(url: &str) -> &str {
+    Path::new(url)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+}
+
+/// Splits `key=value`.
+fn split_pair<'a>(pair: &'a str, what: &str) -> (&'a str, &'a str) {
+    pair.split_once('=')
+        .unwrap_or_else(|| fail(format!("invalid {} {}", what, pair), "expected key=value"))
+}
+
+fn fail<E: Display>(context: String, err: E) -> ! {
+    eprintln!("{}: {}", context, err);
+    process::exit(1);
+}
+
+#[test]
+fn test_url_for() {
+    let root = Path::new("site");
+    assert_eq!(
+        url_for(root, &root.join("A").join("index.html")).as_deref(),
+        Some("A/index.html")
+    );
+    assert_eq!(url_for(root, Path::new("elsewhere")), None);
+}
@@ -14,6 +14,11 @@ fn main() {
                 .required(true)
                 .index(1),
         )
+        .arg(
+            Arg::with_name("clusters")
+                .long("clusters")
+                .help("Print the compression and offset size of every cluster"),
+        )
         .get_matches();
 
     let input = matches.value_of("INPUT").unwrap();
@@ -22,15 +27,24 @@ fn main() {
 
     let zim_file = Zim::new(input).expect("failed to parse input");
 
-    println!("UUID: {}", &zim_file.header.uuid);
-    println!("Article Count: {}", zim_file.article_count());
+    println!("{}", zim_file.header);
     println!("Mime List Pos: {}", zim_file.header.mime_list_pos);
     println!("URL Pointer Pos: {}", zim_file.header.url_ptr_pos);
     println!("Title Index Pos: {}", zim_file.header.title_ptr_pos);
-    println!("Cluster Count: {}", zim_file.header.cluster_count);
     println!("Cluster Pointer Pos: {}", zim_file.header.cluster_ptr_pos);
     println!("Checksum: {}", hex::encode(&zim_file.checksum));
-    println!("Checksum Pos: {}", zim_file.header.checksum_pos);
+    println!("Checksum Type: {:?}", zim_file.header.checksum_type);
+
+    if zim_file.has_unified_namespace() {
+        println!("Namespace Scheme: unified (C)");
+    } else {
+        println!("Namespace Scheme: legacy (A, I, J)");
+    }
+
+    println!("Mime Types: {}", zim_file.mime_type_count());
+    for (idx, mime) in zim_file.mime_types().enumerate() {
+        println!("  {}: {}", idx, mime);
+    }
 
     let (main_page, main_page_idx) = if let Some(main_page_idx) = zim_file.header.main_page {
         let page = zim_file
@@ -59,4 +73,20 @@ fn main() {
         "Layout page: '{}' (index: {})",
         layout_page, layout_page_idx
     );
+
+    if matches.is_present("clusters") {
+        println!("\nClusters:");
+        for (idx, cluster) in zim_file.iterate_clusters() {
+            let cluster = cluster.expect("failed to get cluster");
+
+            println!(
+                "{}: pos {}, compression {:?}, extended {} (info byte: {:#04x})",
+                idx,
+                zim_file.cluster_list[idx as usize],
+                cluster.compression(),
+                cluster.is_extended(),
+                cluster.info_byte()
+            );
+        }
+    }
 }
@@ -0,0 +1,96 @@
+use std::process;
+
+use clap::{App, Arg};
+use serde_json::json;
+use zim::{Namespace, Zim};
+
+fn main() {
+    let matches = App::new("zim_search")
+        .version("0.1")
+        .about("Search zim files for titles starting with a prefix")
+        .arg(
+            Arg::with_name("namespace")
+                .long("namespace")
+                .help("Only search the namespace with this character, e.g. A")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("limit")
+                .long("limit")
+                .help("Print at most this many matches")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .help("Print the matches as a JSON array")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("case-insensitive")
+                .long("case-insensitive")
+                .help("Ignore case, which scans all titles instead of a binary search")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("INPUT")
+                .help("The zim file to search")
+                .required(true)
+                .index(1),
+        )
+        .arg(
+            Arg::with_name("QUERY")
+                .help("The title prefix to search for")
+                .required(true)
+                .index(2),
+        )
+        .get_matches();
+
+    let namespace = matches.value_of("namespace").map(|ns| {
+        ns.parse::<Namespace>().unwrap_or_else(|_| {
+            eprintln!("invalid namespace: {}", ns);
+            process::exit(1);
+        })
+    });
+    let limit = matches.value_of("limit").map(|limit| {
+        limit.parse::<usize>().unwrap_or_else(|_| {
+            eprintln!("invalid limit: {}", limit);
+            process::exit(1);
+        })
+    });
+    let json = matches.is_present("json");
+    let case_insensitive = matches.is_present("case-insensitive");
+    let input = matches.value_of("INPUT").unwrap();
+    let query = matches.value_of("QUERY").unwrap();
+
+    let zim_file = Zim::new(input).expect("failed to parse input");
+    let mut entries = zim_file
+        .title_prefix_matches(query, namespace, case_insensitive)
+        .expect("failed to search titles");
+
+    let total = entries.len();
+    if let Some(limit) = limit {
+        entries.truncate(limit);
+    }
+
+    if json {
+        let entries: Vec<_> = entries
+            .iter()
+            .map(|entry| {
+                json!({
+                    "namespace": entry.namespace.to_string(),
+                    "url": entry.url,
+                    "title": entry.title,
+                })
+            })
+            .collect();
+        println!("{}", serde_json::Value::from(entries));
+    } else {
+        for entry in &entries {
+            println!("{}\t{}", entry.full_url(), entry.title);
+        }
+    }
+
+    // on stderr, so the output can be piped
+    eprintln!("{} of {} matches", entries.len(), total);
+}
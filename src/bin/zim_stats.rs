@@ -0,0 +1,297 @@
+use std::collections::{BTreeMap, HashMap};
+use std::process;
+
+use clap::{App, Arg};
+use indicatif::HumanBytes;
+use rayon::prelude::*;
+use serde_json::{json, Value};
+use zim::{MimeType, Namespace, Target, Zim};
+
+/// Counts and sizes of the entries in one namespace.
+#[derive(Default)]
+struct NamespaceStats {
+    entries: u64,
+    /// Sum of the blob sizes, only for entries in examined clusters.
+    size: u64,
+}
+
+/// Sizes of one examined cluster.
+struct ClusterStats {
+    idx: u32,
+    compressed: u64,
+    uncompressed: u64,
+    /// Blob sizes by blob index.
+    blobs: Vec<u64>,
+}
+
+impl ClusterStats {
+    fn ratio(&self) -> f64 {
+        self.uncompressed as f64 / self.compressed.max(1) as f64
+    }
+}
+
+/// Summary of a list of values.
+struct Distribution {
+    min: f64,
+    max: f64,
+    mean: f64,
+    p50: f64,
+    p95: f64,
+}
+
+impl Distribution {
+    fn new(mut values: Vec<f64>) -> Option<Distribution> {
+        if values.is_empty() {
+            return None;
+        }
+        values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+        // nearest rank
+        let percentile = |p: usize| values[(values.len() - 1) * p / 100];
+        Some(Distribution {
+            min: values[0],
+            max: values[values.len() - 1],
+            mean: values.iter().sum::<f64>() / values.len() as f64,
+            p50: percentile(50),
+            p95: percentile(95),
+        })
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "min": self.min,
+            "max": self.max,
+            "mean": self.mean,
+            "p50": self.p50,
+            "p95": self.p95,
+        })
+    }
+}
+
+fn main() {
+    let matches = App::new("zim_stats")
+        .version("0.1")
+        .about("Report statistics about zim files")
+        .arg(
+            Arg::with_name("sample")
+                .long("sample")
+                .help("Only examine every Nth cluster, for approximate statistics")
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .help("Print the statistics as JSON")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("INPUT")
+                .help("The zim file to inspect")
+                .required(true)
+                .index(1),
+        )
+        .get_matches();
+
+    let sample = match matches.value_of("sample").map(str::parse::<usize>) {
+        None => 1,
+        Some(Ok(sample)) if sample > 0 => sample,
+        Some(_) => {
+            eprintln!("--sample must be a positive number");
+            process::exit(1);
+        }
+    };
+    let json = matches.is_present("json");
+    let input = matches.value_of("INPUT").unwrap();
+
+    let zim_file = Zim::new(input).expect("failed to parse input");
+
+    let examined: Vec<u32> = (0..zim_file.header.cluster_count).step_by(sample).collect();
+    let clusters: Vec<ClusterStats> = examined
+        .par_iter()
+        .filter_map(|&idx| match cluster_stats(&zim_file, idx) {
+            Ok(stats) => Some(stats),
+            Err(err) => {
+                eprintln!("skipping cluster {}: {}", idx, err);
+                None
+            }
+        })
+        .collect();
+    let blob_sizes: HashMap<u32, &[u64]> = clusters
+        .iter()
+        .map(|cluster| (cluster.idx, &cluster.blobs[..]))
+        .collect();
+
+    let mut namespaces: BTreeMap<Namespace, NamespaceStats> = BTreeMap::new();
+    let mut mime_types: BTreeMap<String, u64> = BTreeMap::new();
+    let mut redirects = 0;
+    let mut deleted = 0;
+    let mut unreadable = 0;
+    for entry in zim_file.try_iterate_by_urls() {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(_) => {
+                unreadable += 1;
+                continue;
+            }
+        };
+
+        let stats = namespaces.entry(entry.namespace).or_default();
+        stats.entries += 1;
+        if let Some(Target::Cluster(cluster_idx, blob_idx)) = entry.target {
+            if let Some(size) = blob_sizes
+                .get(&cluster_idx)
+                .and_then(|blobs| blobs.get(blob_idx as usize))
+            {
+                stats.size += size;
+            }
+        }
+
+        let mime = match entry.mime_type {
+            MimeType::Type(mime) => mime,
+            MimeType::Redirect => {
+                redirects += 1;
+                "redirect".into()
+            }
+            MimeType::LinkTarget => "link target".into(),
+            MimeType::DeletedEntry => {
+                deleted += 1;
+                "deleted".into()
+            }
+        };
+        *mime_types.entry(mime).or_default() += 1;
+    }
+
+    let compressed: u64 = clusters.iter().map(|cluster| cluster.compressed).sum();
+    let uncompressed: u64 = clusters.iter().map(|cluster| cluster.uncompressed).sum();
+    let sizes = Distribution::new(
+        clusters
+            .iter()
+            .map(|cluster| cluster.compressed as f64)
+            .collect(),
+    );
+    let ratios = Distribution::new(clusters.iter().map(ClusterStats::ratio).collect());
+
+    if json {
+        let report = json!({
+            "file": input,
+            "sample": sample,
+            "clusters_examined": clusters.len(),
+            "cluster_count": zim_file.header.cluster_count,
+            "entries": zim_file.article_count(),
+            "unreadable_entries": unreadable,
+            "redirects": redirects,
+            "deleted": deleted,
+            "namespaces": namespaces
+                .iter()
+                .map(|(namespace, stats)| {
+                    (
+                        namespace.to_string(),
+                        json!({ "entries": stats.entries, "size": stats.size }),
+                    )
+                })
+                .collect::<serde_json::Map<_, _>>(),
+            "mime_types": mime_types,
+            "cluster_sizes": sizes.as_ref().map(Distribution::to_json),
+            "compression_ratios": ratios.as_ref().map(Distribution::to_json),
+            "clusters": clusters
+                .iter()
+                .map(|cluster| {
+                    json!({
+                        "idx": cluster.idx,
+                        "compressed": cluster.compressed,
+                        "uncompressed": cluster.uncompressed,
+                        "ratio": cluster.ratio(),
+                    })
+                })
+                .collect::<Vec<_>>(),
+            "compressed": compressed,
+            "uncompressed": uncompressed,
+            "file_size": zim_file.file_size(),
+            "cluster_data_size": zim_file.total_compressed_size(),
+            "metadata_overhead": zim_file.metadata_overhead_bytes(),
+        });
+        println!("{}", report);
+        return;
+    }
+
+    println!("Statistics for: {}\n", input);
+    if sample > 1 {
+        println!(
+            "Sizes only include the {} of {} clusters examined with --sample {}\n",
+            clusters.len(),
+            zim_file.header.cluster_count,
+            sample
+        );
+    }
+
+    println!(
+        "File size: {} ({} in clusters, {} other data)",
+        HumanBytes(zim_file.file_size()),
+        HumanBytes(zim_file.total_compressed_size()),
+        HumanBytes(zim_file.metadata_overhead_bytes())
+    );
+    println!("Entries: {}", zim_file.article_count());
+    if unreadable > 0 {
+        println!("Unreadable entries: {}", unreadable);
+    }
+    println!("Redirects: {}", redirects);
+    println!("Deleted entries: {}", deleted);
+
+    println!("\n{:<10} {:>10} {:>12}", "Namespace", "Entries", "Size");
+    for (namespace, stats) in &namespaces {
+        println!(
+            "{:<10} {:>10} {:>12}",
+            namespace.to_string(),
+            stats.entries,
+            HumanBytes(stats.size).to_string()
+        );
+    }
+
+    println!("\n{:<40} {:>10}", "MIME type", "Entries");
+    for (mime, count) in &mime_types {
+        println!("{:<40} {:>10}", mime, count);
+    }
+
+    println!(
+        "\n{:<20} {:>12} {:>12} {:>12} {:>12} {:>12}",
+        "", "min", "max", "mean", "p50", "p95"
+    );
+    if let Some(sizes) = sizes {
+        println!(
+            "{:<20} {:>12} {:>12} {:>12} {:>12} {:>12}",
+            "Cluster size",
+            HumanBytes(sizes.min as u64).to_string(),
+            HumanBytes(sizes.max as u64).to_string(),
+            HumanBytes(sizes.mean as u64).to_string(),
+            HumanBytes(sizes.p50 as u64).to_string(),
+            HumanBytes(sizes.p95 as u64).to_string()
+        );
+    }
+    if let Some(ratios) = ratios {
+        println!(
+            "{:<20} {:>12.2} {:>12.2} {:>12.2} {:>12.2} {:>12.2}",
+            "Compression ratio", ratios.min, ratios.max, ratios.mean, ratios.p50, ratios.p95
+        );
+    }
+
+    println!(
+        "\nCompressed: {}, uncompressed: {} (ratio {:.2})",
+        HumanBytes(compressed),
+        HumanBytes(uncompressed),
+        uncompressed as f64 / compressed.max(1) as f64
+    );
+}
+
+fn cluster_stats(zim_file: &Zim, idx: u32) -> zim::Result<ClusterStats> {
+    let cluster = zim_file.get_cluster(idx)?;
+    let blobs = (0..cluster.blob_count()?)
+        .map(|blob_idx| cluster.map_blob(blob_idx, |blob| blob.len() as u64))
+        .collect::<zim::Result<_>>()?;
+
+    Ok(ClusterStats {
+        idx,
+        compressed: cluster.compressed_size(),
+        uncompressed: cluster.decompressed_size()? as u64,
+        blobs,
+    })
+}
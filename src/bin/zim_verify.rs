@@ -0,0 +1,161 @@
+use std::process;
+
+use clap::{App, Arg};
+use serde_json::json;
+use zim::{Error, ValidationWarning, WarningKind, Zim};
+
+/// Exit code when the checksum does not match.
+const EXIT_CHECKSUM: i32 = 1;
+/// Exit code when the file can't be parsed or `validate` found errors.
+const EXIT_STRUCTURE: i32 = 2;
+/// Exit code when the file can't be read.
+const EXIT_IO: i32 = 3;
+
+fn main() {
+    let matches = App::new("zim_verify")
+        .version("0.1")
+        .about("Verify the integrity of zim files")
+        .arg(
+            Arg::with_name("strict")
+                .long("strict")
+                .help("Fail on warnings, not only on errors")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("json")
+                .long("json")
+                .help("Print the report as JSON")
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("INPUT")
+                .help("The zim file to verify")
+                .required(true)
+                .index(1),
+        )
+        .get_matches();
+
+    let strict = matches.is_present("strict");
+    let json = matches.is_present("json");
+    let input = matches.value_of("INPUT").unwrap();
+
+    let zim_file = match Zim::new(input) {
+        Ok(zim_file) => zim_file,
+        Err(err) => fail(input, &err, json),
+    };
+
+    // the checksum reads the whole file once, which the kernel can read ahead for
+    if let Err(err) = zim_file.advise_sequential() {
+        eprintln!("failed to advise sequential access: {}", err);
+    }
+    let checksum = zim_file.verify_checksum();
+    if let Err(ref err @ Error::Io(_)) = checksum {
+        fail(input, err, json);
+    }
+
+    let warnings = match zim_file.validate() {
+        Ok(warnings) => warnings,
+        Err(err) => fail(input, &err, json),
+    };
+    let failures = warnings
+        .iter()
+        .filter(|warning| strict || severity(warning.kind) == "error")
+        .count();
+
+    let code = if checksum.is_err() {
+        EXIT_CHECKSUM
+    } else if failures > 0 {
+        EXIT_STRUCTURE
+    } else {
+        0
+    };
+
+    if json {
+        print_json(input, &checksum, &warnings, code);
+    } else {
+        print_report(input, &checksum, &warnings);
+    }
+
+    process::exit(code);
+}
+
+/// Errors make the file unusable in parts, warnings only affect single entries or metadata.
+fn severity(kind: WarningKind) -> &'static str {
+    match kind {
+        WarningKind::InvalidMimeType | WarningKind::InvalidPageIndex => "warning",
+        _ => "error",
+    }
+}
+
+fn print_report(input: &str, checksum: &Result<(), Error>, warnings: &[ValidationWarning]) {
+    println!("Verifying: {}\n", input);
+
+    match checksum {
+        Ok(()) => println!("Checksum: ok"),
+        Err(err) => println!("Checksum: FAILED ({})", err),
+    }
+
+    for warning in warnings {
+        println!(
+            "{}: {:?}: {}",
+            severity(warning.kind),
+            warning.kind,
+            warning.message
+        );
+    }
+
+    let errors = warnings
+        .iter()
+        .filter(|warning| severity(warning.kind) == "error")
+        .count();
+    println!("\n{} errors, {} warnings", errors, warnings.len() - errors);
+}
+
+fn print_json(
+    input: &str,
+    checksum: &Result<(), Error>,
+    warnings: &[ValidationWarning],
+    code: i32,
+) {
+    let warnings: Vec<_> = warnings
+        .iter()
+        .map(|warning| {
+            json!({
+                "severity": severity(warning.kind),
+                "kind": format!("{:?}", warning.kind),
+                "index": warning.cluster_or_entry_idx,
+                "message": warning.message,
+            })
+        })
+        .collect();
+
+    let report = json!({
+        "file": input,
+        "valid": code == 0,
+        "checksum": {
+            "valid": checksum.is_ok(),
+            "error": checksum.as_ref().err().map(|err| err.to_string()),
+        },
+        "warnings": warnings,
+    });
+    println!("{}", report);
+}
+
+/// Reports an error which stops the verification, and exits.
+fn fail(input: &str, err: &Error, json: bool) -> ! {
+    let code = match err {
+        Error::Io(_) => EXIT_IO,
+        _ => EXIT_STRUCTURE,
+    };
+
+    if json {
+        println!(
+            "{}",
+            json!({ "file": input, "valid": false, "error": err.to_string() })
+        );
+    } else {
+        eprintln!("failed to verify {}: {}", input, err);
+    }
+
+    process::exit(code);
+}
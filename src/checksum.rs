@@ -0,0 +1,105 @@
+use std::fmt;
+
+use md5::{Digest, Md5};
+#[cfg(feature = "sha2")]
+use sha2::Sha256;
+
+use crate::errors::{Error, Result};
+
+/// The algorithm used for the checksum at the end of a ZIM file.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ChecksumType {
+    /// 16 byte MD5 digest, used by all version 5 files
+    Md5,
+    /// 32 byte SHA-256 digest, requires the `sha2` feature to verify
+    Sha256,
+}
+
+impl ChecksumType {
+    /// Determines the checksum type from the number of bytes following `checksum_pos`.
+    pub fn from_len(len: u64) -> ChecksumType {
+        match len {
+            32 => ChecksumType::Sha256,
+            _ => ChecksumType::Md5,
+        }
+    }
+
+    /// Size of the digest in bytes.
+    pub fn digest_len(self) -> usize {
+        match self {
+            ChecksumType::Md5 => 16,
+            ChecksumType::Sha256 => 32,
+        }
+    }
+}
+
+/// A checksum digest, independent of the algorithm that produced it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ChecksumValue(Vec<u8>);
+
+impl ChecksumValue {
+    pub fn new(raw: Vec<u8>) -> Self {
+        ChecksumValue(raw)
+    }
+}
+
+impl AsRef<[u8]> for ChecksumValue {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl fmt::Display for ChecksumValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&hex::encode(&self.0))
+    }
+}
+
+/// Read out the checksum stored at `checksum_pos`.
+pub(crate) fn read_checksum(
+    master_view: &[u8],
+    checksum_pos: u64,
+    checksum_type: ChecksumType,
+) -> Result<ChecksumValue> {
+    let start = checksum_pos as usize;
+    match master_view.get(start..start + checksum_type.digest_len()) {
+        Some(raw) => Ok(ChecksumValue(raw.to_vec())),
+        None => Err(Error::MissingChecksum),
+    }
+}
+
+/// Compute the checksum of the file in `master_view`, up to (not including) the checksum
+/// itself.
+pub(crate) fn compute_checksum(
+    master_view: &[u8],
+    checksum_pos: u64,
+    checksum_type: ChecksumType,
+) -> Result<ChecksumValue> {
+    let data = master_view
+        .get(..checksum_pos as usize)
+        .ok_or(Error::OutOfBounds)?;
+
+    match checksum_type {
+        ChecksumType::Md5 => Ok(ChecksumValue(Md5::digest(data).to_vec())),
+        #[cfg(feature = "sha2")]
+        ChecksumType::Sha256 => Ok(ChecksumValue(Sha256::digest(data).to_vec())),
+        #[cfg(not(feature = "sha2"))]
+        ChecksumType::Sha256 => Err(Error::UnsupportedChecksum),
+    }
+}
+
+#[cfg(feature = "sha2")]
+#[test]
+fn test_sha256_checksum() {
+    let mut data = b"not really a zim file".to_vec();
+    let checksum_pos = data.len() as u64;
+    data.extend_from_slice(&Sha256::digest(&data));
+
+    let checksum_type = ChecksumType::from_len(data.len() as u64 - checksum_pos);
+    assert_eq!(checksum_type, ChecksumType::Sha256);
+
+    let stored = read_checksum(&data, checksum_pos, checksum_type).unwrap();
+    let computed = compute_checksum(&data, checksum_pos, checksum_type).unwrap();
+
+    assert_eq!(stored, computed);
+}
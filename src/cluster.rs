@@ -1,19 +1,34 @@
 use std::fmt;
+use std::io;
 use std::io::Cursor;
-use std::io::Read;
-use std::sync::{Arc, RwLock};
+use std::io::{Read, Write};
+use std::mem;
+use std::ops::{Deref, Range};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex, RwLock, RwLockReadGuard};
 
 use bitreader::BitReader;
-use byteorder::{LittleEndian, ReadBytesExt};
-use memmap::Mmap;
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+#[cfg(feature = "bzip2")]
+use bzip2::read::BzDecoder;
+#[cfg(feature = "bzip2")]
+use bzip2::write::BzEncoder;
+#[cfg(not(feature = "wasm"))]
 use xz2::read::XzDecoder;
+#[cfg(not(feature = "wasm"))]
+use xz2::write::XzEncoder;
 
 use crate::errors::{Error, Result};
+use crate::zim::file_region;
 
 #[repr(u8)]
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
 pub enum Compression {
     None = 0,
+    /// bzip2, inherited from Zeno; requires the `bzip2` feature
+    Bzip2 = 2,
+    /// bzip2 as written by old versions of zimlib; requires the `bzip2` feature
+    Bzip2Old = 3,
     LZMA2 = 4,
 }
 
@@ -28,6 +43,8 @@ impl Compression {
         match raw {
             0 => Ok(Compression::None),
             1 => Ok(Compression::None),
+            2 => Ok(Compression::Bzip2),
+            3 => Ok(Compression::Bzip2Old),
             4 => Ok(Compression::LZMA2),
             _ => Err(Error::UnknownCompression),
         }
@@ -41,21 +58,145 @@ impl Compression {
 #[derive(Clone)]
 pub struct Cluster<'a>(Arc<RwLock<InnerCluster<'a>>>);
 
+static_assertions::assert_impl_all!(
+    Cluster<'static>: std::panic::UnwindSafe,
+    std::panic::RefUnwindSafe
+);
+static_assertions::assert_impl_all!(Cluster<'static>: Send, Sync);
+
 pub struct InnerCluster<'a> {
+    idx: u32,
     extended: bool,
     compression: Compression,
     start: u64,
     end: u64,
     size: u64,
-    view: &'a [u8],
+    view: ClusterBacking<'a>,
     blob_list: Option<Vec<u64>>, // offsets into data
-    decompressed: Option<Vec<u8>>,
+    decompressed: Option<PooledBuffer>,
+    pool: Option<Arc<Mutex<DecompressPool>>>,
+}
+
+/// Decompressed cluster data, which is given back to its pool when dropped.
+struct PooledBuffer {
+    data: Vec<u8>,
+    pool: Option<Arc<Mutex<DecompressPool>>>,
+}
+
+impl PooledBuffer {
+    fn new(pool: Option<Arc<Mutex<DecompressPool>>>) -> Self {
+        let data = match pool {
+            Some(ref pool) => pool.lock().unwrap().take(),
+            None => Vec::new(),
+        };
+        PooledBuffer { data, pool }
+    }
+}
+
+impl Deref for PooledBuffer {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl AsRef<[u8]> for PooledBuffer {
+    fn as_ref(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+impl Drop for PooledBuffer {
+    fn drop(&mut self) {
+        if let Some(pool) = self.pool.take() {
+            if let Ok(mut pool) = pool.lock() {
+                pool.put(mem::take(&mut self.data));
+            }
+        }
+    }
+}
+
+/// Number of decompressed bytes between two progress reports.
+const PROGRESS_STEP: u64 = 64 * 1024;
+
+/// Maximum number of buffers kept by a `DecompressPool`.
+const POOL_SIZE: usize = 16;
+
+/// Buffers for decompressed cluster data, which are reused instead of allocated per cluster.
+///
+/// Clusters created with a pool take their buffer from it, and give it back when dropped.
+#[derive(Debug, Default)]
+pub struct DecompressPool {
+    buffers: Vec<Vec<u8>>,
+}
+
+impl DecompressPool {
+    pub fn new() -> Self {
+        DecompressPool::default()
+    }
+
+    /// Takes an empty buffer out of the pool, or allocates a new one if there is none.
+    pub fn take(&mut self) -> Vec<u8> {
+        self.buffers.pop().unwrap_or_default()
+    }
+
+    /// Returns a buffer to the pool. At most 16 buffers are kept, further ones are dropped.
+    pub fn put(&mut self, mut buffer: Vec<u8>) {
+        if self.buffers.len() < POOL_SIZE {
+            buffer.clear();
+            self.buffers.push(buffer);
+        }
+    }
+
+    /// Number of buffers available.
+    pub fn len(&self) -> usize {
+        self.buffers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buffers.is_empty()
+    }
+}
+
+/// The raw data of a cluster, including the info byte.
+#[derive(Clone)]
+pub enum ClusterBacking<'a> {
+    /// Borrowed from the memory map of a ZIM file
+    Borrowed(&'a [u8]),
+    /// Held by a reference counted buffer, which can be shared between clusters and threads
+    #[cfg(feature = "bytes")]
+    Owned(bytes::Bytes),
+}
+
+impl<'a> ClusterBacking<'a> {
+    /// Returns a reader over `start..end` that does not borrow `self`.
+    fn reader(&self, start: usize, end: usize) -> Box<dyn Read + 'a> {
+        match *self {
+            ClusterBacking::Borrowed(view) => Box::new(Cursor::new(&view[start..end])),
+            #[cfg(feature = "bytes")]
+            ClusterBacking::Owned(ref bytes) => Box::new(Cursor::new(bytes.slice(start..end))),
+        }
+    }
+}
+
+impl<'a> Deref for ClusterBacking<'a> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match *self {
+            ClusterBacking::Borrowed(view) => view,
+            #[cfg(feature = "bytes")]
+            ClusterBacking::Owned(ref bytes) => bytes,
+        }
+    }
 }
 
 impl<'a> fmt::Debug for Cluster<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let raw = self.0.read().unwrap();
         f.debug_struct("Cluster")
+            .field("idx", &raw.idx)
             .field("extended", &raw.extended)
             .field("compression", &raw.compression)
             .field("start", &raw.start)
@@ -72,18 +213,48 @@ impl<'a> fmt::Debug for Cluster<'a> {
 }
 
 impl<'a> Cluster<'a> {
+    /// Creates the cluster with index `idx`, stored at `start..end` in `master_view`.
     pub fn new(
-        master_view: &'a Mmap,
-        cluster_list: &'a Vec<u64>,
+        master_view: &'a [u8],
         idx: u32,
-        checksum_pos: u64,
+        start: u64,
+        end: u64,
         version: u16,
     ) -> Result<Cluster<'a>> {
         Ok(Cluster(Arc::new(RwLock::new(InnerCluster::new(
             master_view,
-            cluster_list,
             idx,
-            checksum_pos,
+            start,
+            end,
+            version,
+        )?))))
+    }
+
+    /// Like `new`, but the buffer for the decompressed data is taken from `pool`.
+    pub fn new_with_pool(
+        master_view: &'a [u8],
+        idx: u32,
+        start: u64,
+        end: u64,
+        version: u16,
+        pool: Arc<Mutex<DecompressPool>>,
+    ) -> Result<Cluster<'a>> {
+        let mut inner = InnerCluster::new(master_view, idx, start, end, version)?;
+        inner.pool = Some(pool);
+        Ok(Cluster(Arc::new(RwLock::new(inner))))
+    }
+
+    /// Creates a cluster from a buffer holding exactly the cluster data.
+    ///
+    /// The buffer is shared, not copied, so the same data can back several readers.
+    #[cfg(feature = "bytes")]
+    pub fn new_from_bytes(data: bytes::Bytes, idx: u32, version: u16) -> Result<Cluster<'a>> {
+        let len = data.len() as u64;
+        Ok(Cluster(Arc::new(RwLock::new(InnerCluster::from_backing(
+            ClusterBacking::Owned(data),
+            idx,
+            0,
+            len,
             version,
         )?))))
     }
@@ -92,28 +263,249 @@ impl<'a> Cluster<'a> {
         self.0.write().unwrap().decompress()
     }
 
-    pub fn get_blob<'b: 'a>(&'b self, idx: u32) -> Result<Blob<'a, 'b>> {
+    /// Like `decompress`, calling `on_progress` with the number of bytes decompressed so far
+    /// and the total, if the xz stream records it.
+    ///
+    /// Clusters which are not compressed or already decompressed report their full size once.
+    pub fn decompress_with_progress<F>(&self, on_progress: F) -> Result<()>
+    where
+        F: Fn(usize, Option<usize>),
+    {
+        self.0.write().unwrap().decompress_with(&on_progress)
+    }
+
+    /// Frees the decompressed data, e.g. once all blobs have been read. The next `get_blob`
+    /// decompresses the cluster again.
+    ///
+    /// Uncompressed clusters are read straight from the file, so there is nothing to free. This
+    /// waits for all `Blob`s of the cluster to be dropped.
+    pub fn drop_decompressed(&self) {
+        let mut inner = self.0.write().unwrap();
+        if inner.compression != Compression::None {
+            inner.decompressed = None;
+            inner.blob_list = None;
+        }
+    }
+
+    /// Returns the number of blobs in this cluster, decompressing it if needed.
+    pub fn blob_count(&self) -> Result<u32> {
+        self.ensure_decompressed()?;
+        Ok(self.0.read().unwrap().blob_count())
+    }
+
+    /// Size of the cluster data without the info byte, as stored in the file.
+    pub fn compressed_size(&self) -> u64 {
+        self.0.read().unwrap().size - 1
+    }
+
+    /// Size of the cluster data after decompression, including the blob offsets.
+    ///
+    /// For LZMA2 clusters the size is read from the index at the end of the xz stream, the
+    /// cluster is only decompressed if that fails.
+    pub fn decompressed_size(&self) -> Result<usize> {
         {
             let lock = self.0.read().unwrap();
-            if lock.needs_decompression() {
-                drop(lock);
-                self.0.write().unwrap().decompress()?;
+            match lock.compression {
+                Compression::None => return Ok(lock.view.len() - 1),
+                compression => {
+                    if let Some(ref data) = lock.decompressed {
+                        return Ok(data.len());
+                    }
+                    if compression == Compression::LZMA2 {
+                        if let Some(size) = xz_uncompressed_size(&lock.view[1..]) {
+                            return Ok(size as usize);
+                        }
+                    }
+                }
             }
         }
 
-        match Blob::try_new(self.0.read().unwrap(), |lock| lock.get_blob(idx)) {
-            Ok(blob) => Ok(blob),
+        self.ensure_decompressed()?;
+        let lock = self.0.read().unwrap();
+        Ok(lock.decompressed.as_ref().map_or(0, |data| data.len()))
+    }
+
+    /// Whether the blob offsets are 8 instead of 4 bytes wide.
+    pub fn is_extended(&self) -> bool {
+        self.0.read().unwrap().extended
+    }
+
+    pub fn compression(&self) -> Compression {
+        self.0.read().unwrap().compression
+    }
+
+    /// The raw info byte, which holds the compression in the lower four bits and the extended
+    /// flag in the fifth bit.
+    pub fn info_byte(&self) -> u8 {
+        // checked to exist when the cluster was created
+        self.0.read().unwrap().view[0]
+    }
+
+    pub fn get_blob<'b: 'a>(&'b self, idx: u32) -> Result<Blob<'a, 'b>> {
+        self.ensure_decompressed()?;
+
+        match BlobGuard::try_new(self.0.read().unwrap(), |lock| lock.get_blob(idx)) {
+            Ok(guard) => Ok(Blob::new(BlobData::Locked(guard))),
             Err(rental::RentalError(err, _)) => Err(err),
         }
     }
+
+    /// Calls `f` with the data of the given blob, returning its result.
+    ///
+    /// Unlike `get_blob`, the result does not borrow the cluster, which makes it easy to return
+    /// owned values, e.g. `cluster.map_blob(idx, |blob| blob.to_vec())`.
+    pub fn map_blob<F, T>(&self, idx: u32, f: F) -> Result<T>
+    where
+        F: FnOnce(&[u8]) -> T,
+    {
+        self.ensure_decompressed()?;
+
+        let lock = self.0.read().unwrap();
+        Ok(f(lock.get_blob(idx)?))
+    }
+
+    /// Returns the blobs at all of `indices`, in the same order.
+    ///
+    /// The cluster is locked only once, instead of once per blob, and stays locked for reading
+    /// until all returned blobs are dropped. The indices don't need to be sorted, and invalid ones
+    /// give an `Error::InvalidBlobIndex` at their position. Only a failure to decompress the
+    /// cluster fails the whole call.
+    pub fn get_blobs<'b: 'a>(&'b self, indices: &[u32]) -> Result<Vec<Result<Blob<'a, 'b>>>> {
+        self.ensure_decompressed()?;
+
+        let guard = Rc::new(self.0.read().unwrap());
+        Ok(indices
+            .iter()
+            .map(|&idx| {
+                guard
+                    .blob_bounds(idx)
+                    .map(|range| Blob::new(BlobData::Shared(guard.clone(), range)))
+            })
+            .collect())
+    }
+
+    /// Returns the given blob, taking over the data of this cluster.
+    ///
+    /// Unlike `get_blob`, the blob does not borrow the cluster. Blobs of uncompressed clusters
+    /// borrow from the file, and the decompressed data of other clusters is moved into the blob.
+    /// The blob is only copied if the cluster is shared with a clone.
+    pub fn into_blob<'b>(self, idx: u32) -> Result<Blob<'a, 'b>> {
+        self.ensure_decompressed()?;
+
+        let inner = match Arc::try_unwrap(self.0) {
+            Ok(lock) => lock.into_inner().unwrap(),
+            Err(shared) => {
+                let lock = shared.read().unwrap();
+                return Ok(Blob::new(BlobData::Owned(lock.get_blob(idx)?.to_vec())));
+            }
+        };
+        let range = inner.blob_bounds(idx)?;
+        // the blob offsets of uncompressed clusters start after the info byte
+        let data = match (inner.decompressed, inner.view) {
+            (Some(data), _) => BlobData::Detached(data, range),
+            (None, ClusterBacking::Borrowed(view)) => {
+                BlobData::Borrowed(&view[1 + range.start..1 + range.end])
+            }
+            #[cfg(feature = "bytes")]
+            (None, ClusterBacking::Owned(bytes)) => {
+                BlobData::Bytes(bytes.slice(1 + range.start..1 + range.end))
+            }
+        };
+        Ok(Blob::new(data))
+    }
+
+    /// Returns a reader over the given blob without decompressing the whole cluster.
+    ///
+    /// Uncompressed blobs are read straight from the file; compressed clusters are decoded only
+    /// up to the end of the requested blob.
+    pub fn blob_reader(&self, idx: u32) -> Result<Box<dyn Read + 'a>> {
+        self.0.read().unwrap().blob_reader(idx)
+    }
+
+    /// Iterates over all blobs of this cluster, in blob index order.
+    ///
+    /// The cluster is decompressed here, and then stays locked for reading until the iterator
+    /// and all blobs it returned are dropped, so the blobs are read without locking again. If
+    /// decompressing fails the error is yielded once and the iteration ends.
+    pub fn iter_blobs<'b: 'a>(&'b self) -> BlobIter<'a, 'b> {
+        match self.ensure_decompressed() {
+            Ok(()) => {
+                let guard = self.0.read().unwrap();
+                BlobIter {
+                    count: guard.blob_count(),
+                    guard: Some(Rc::new(guard)),
+                    error: None,
+                    next: 0,
+                }
+            }
+            Err(err) => BlobIter {
+                guard: None,
+                error: Some(err),
+                next: 0,
+                count: 0,
+            },
+        }
+    }
+
+    fn ensure_decompressed(&self) -> Result<()> {
+        let lock = self.0.read().unwrap();
+        if lock.needs_decompression() {
+            drop(lock);
+            self.0.write().unwrap().decompress()?;
+        }
+
+        Ok(())
+    }
 }
 
+/// Iterator over the blobs of a `Cluster`, created by `Cluster::iter_blobs`.
+pub struct BlobIter<'a, 'b> {
+    /// Shared with the blobs returned so far.
+    guard: Option<Rc<RwLockReadGuard<'b, InnerCluster<'a>>>>,
+    /// The decompression error, which is yielded instead of the blobs.
+    error: Option<Error>,
+    next: u32,
+    count: u32,
+}
+
+impl<'a, 'b: 'a> Iterator for BlobIter<'a, 'b> {
+    type Item = Result<Blob<'a, 'b>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(err) = self.error.take() {
+            return Some(Err(err));
+        }
+
+        let guard = self.guard.as_ref()?;
+        if self.next >= self.count {
+            // the blobs still returned keep the cluster locked
+            self.guard = None;
+            return None;
+        }
+
+        let idx = self.next;
+        self.next += 1;
+
+        let blob = guard
+            .blob_bounds(idx)
+            .map(|range| Blob::new(BlobData::Shared(guard.clone(), range)));
+        Some(blob)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.count - self.next) as usize + self.error.is_some() as usize;
+        (len, Some(len))
+    }
+}
+
+impl<'a, 'b: 'a> ExactSizeIterator for BlobIter<'a, 'b> {}
+
 rental! {
     pub mod rents {
         use super::*;
 
         #[rental(deref_suffix)]
-        pub struct Blob<'a, 'b>  {
+        pub struct BlobGuard<'a, 'b>  {
             #[target_ty = "InnerCluster<'a>"]
             guard: std::sync::RwLockReadGuard<'b, InnerCluster<'a>>,
             slice: &'guard [u8],
@@ -123,28 +515,112 @@ rental! {
 
 use rents::*;
 
+/// The data of a blob, created by `Cluster::get_blob`, `Cluster::into_blob` or
+/// `Zim::get_blob_for_entry`.
+///
+/// Derefs to the whole blob. It can also be read as a stream, e.g. with `io::copy`, which keeps
+/// track of the position without copying the data first.
+pub struct Blob<'a, 'b> {
+    data: BlobData<'a, 'b>,
+    /// Position of the next `read`.
+    pos: usize,
+}
+
+enum BlobData<'a, 'b> {
+    /// Borrowed from the cluster, which stays locked for reading meanwhile.
+    Locked(BlobGuard<'a, 'b>),
+    /// Copied out of a cluster which is shared with a clone.
+    Owned(Vec<u8>),
+    /// Borrowed from the memory map of a ZIM file, for blobs of uncompressed clusters.
+    Borrowed(&'a [u8]),
+    /// Part of the buffer shared by an uncompressed cluster.
+    #[cfg(feature = "bytes")]
+    Bytes(bytes::Bytes),
+    /// The byte range of the blob in the decompressed data, taken over from a cluster which has
+    /// been dropped since.
+    Detached(PooledBuffer, Range<usize>),
+    /// The byte range of the blob in the cluster data, which is locked for reading once for
+    /// all blobs of a `BlobIter` or of `Cluster::get_blobs`.
+    Shared(Rc<RwLockReadGuard<'b, InnerCluster<'a>>>, Range<usize>),
+}
+
+impl<'a, 'b> Blob<'a, 'b> {
+    fn new(data: BlobData<'a, 'b>) -> Blob<'a, 'b> {
+        Blob { data, pos: 0 }
+    }
+
+    /// The size of the whole blob, regardless of how much has been read.
+    pub fn len(&self) -> usize {
+        self.deref().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl<'a, 'b> Deref for Blob<'a, 'b> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self.data {
+            BlobData::Locked(ref guard) => guard,
+            BlobData::Owned(ref data) => data,
+            BlobData::Borrowed(data) => data,
+            #[cfg(feature = "bytes")]
+            BlobData::Bytes(ref data) => data,
+            BlobData::Detached(ref data, ref range) => &data[range.clone()],
+            BlobData::Shared(ref guard, ref range) => &guard.data()[range.clone()],
+        }
+    }
+}
+
+impl<'a, 'b> Read for Blob<'a, 'b> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = (&self.deref()[self.pos..]).read(buf)?;
+        self.pos += n;
+        Ok(n)
+    }
+}
+
+impl<'a, 'b> io::BufRead for Blob<'a, 'b> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        Ok(&(**self)[self.pos..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.len());
+    }
+}
+
 impl<'a> InnerCluster<'a> {
-    fn new(
-        master_view: &'a Mmap,
-        cluster_list: &'a Vec<u64>,
+    fn new(master_view: &'a [u8], idx: u32, start: u64, end: u64, version: u16) -> Result<Self> {
+        if end <= start {
+            return Err(Error::CorruptCluster {
+                cluster_idx: idx,
+                byte_offset: 0,
+                detail: format!("cluster ends at {} before it starts at {}", end, start),
+            });
+        }
+        let cluster_view = file_region(master_view, start, end)?;
+
+        Self::from_backing(
+            ClusterBacking::Borrowed(cluster_view),
+            idx,
+            start,
+            end,
+            version,
+        )
+    }
+
+    fn from_backing(
+        cluster_view: ClusterBacking<'a>,
         idx: u32,
-        checksum_pos: u64,
+        start: u64,
+        end: u64,
         version: u16,
     ) -> Result<Self> {
-        let idx = idx as usize;
-        let start = cluster_list[idx];
-        let end = if idx < cluster_list.len() - 1 {
-            cluster_list[idx + 1]
-        } else {
-            checksum_pos
-        };
-
-        assert!(end > start);
         let cluster_size = end - start;
-        let cluster_view = master_view
-            .get(start as usize..end as usize)
-            .ok_or(Error::OutOfBounds)?;
-
         let (extended, compression) =
             parse_details(cluster_view.get(0).ok_or(Error::OutOfBounds)?)?;
 
@@ -161,6 +637,7 @@ impl<'a> InnerCluster<'a> {
         };
 
         Ok(Self {
+            idx,
             extended: extended,
             compression: compression,
             start: start,
@@ -169,65 +646,377 @@ impl<'a> InnerCluster<'a> {
             view: cluster_view,
             decompressed: None,
             blob_list,
+            pool: None,
         })
     }
 
     fn needs_decompression(&self) -> bool {
         match self.compression {
-            Compression::LZMA2 => self.decompressed.is_none() || self.blob_list.is_none(),
             Compression::None => false,
+            _ => self.decompressed.is_none() || self.blob_list.is_none(),
         }
     }
 
     fn decompress(&mut self) -> Result<()> {
-        match self.compression {
-            Compression::LZMA2 => {
-                if self.decompressed.is_none() {
-                    let mut decoder = XzDecoder::new(&self.view[1..]);
-                    let mut d = Vec::with_capacity(self.view.len());
-                    decoder.read_to_end(&mut d)?;
-                    self.decompressed = Some(d);
+        self.decompress_with(&|_, _| {})
+    }
+
+    fn decompress_with(&mut self, on_progress: &dyn Fn(usize, Option<usize>)) -> Result<()> {
+        match (self.compression, &self.decompressed) {
+            (Compression::None, _) => {
+                let size = self.view.len() - 1;
+                on_progress(size, Some(size));
+            }
+            (_, Some(d)) => on_progress(d.len(), Some(d.len())),
+            #[cfg(not(feature = "wasm"))]
+            (Compression::LZMA2, None) => {
+                let total = xz_uncompressed_size(&self.view[1..]).map(|size| size as usize);
+                let mut decoder = XzDecoder::new(&self.view[1..]);
+                let d = self.decode(&mut decoder, XzDecoder::total_in, total, on_progress)?;
+                self.decompressed = Some(d);
+            }
+            #[cfg(feature = "wasm")]
+            (Compression::LZMA2, None) => {
+                let total = xz_uncompressed_size(&self.view[1..]).map(|size| size as usize);
+                let mut d = PooledBuffer::new(self.pool.clone());
+                let mut out = ProgressWriter {
+                    data: &mut d.data,
+                    total,
+                    on_progress,
+                };
+                self.xz_decompress(&self.view[1..], &mut out)?;
+                // the last report of `ProgressWriter` was at a step boundary
+                if d.len() as u64 % PROGRESS_STEP != 0 {
+                    on_progress(d.len(), total);
                 }
+                self.decompressed = Some(d);
+            }
+            #[cfg(feature = "bzip2")]
+            (Compression::Bzip2, None) | (Compression::Bzip2Old, None) => {
+                let mut decoder = BzDecoder::new(&self.view[1..]);
+                let d = self.decode(&mut decoder, BzDecoder::total_in, None, on_progress)?;
+                self.decompressed = Some(d);
+            }
+            #[cfg(not(feature = "bzip2"))]
+            (Compression::Bzip2, None) | (Compression::Bzip2Old, None) => {
+                return Err(Error::UnsupportedCompression);
             }
-            Compression::None => {}
         }
 
         if self.blob_list.is_none() {
-            match self.compression {
-                Compression::LZMA2 => {
-                    let cur = Cursor::new(self.decompressed.as_ref().unwrap());
-                    let blob_list = parse_blob_list(cur, self.extended)?;
-                    self.blob_list = Some(blob_list);
-                }
-                Compression::None => {}
+            if let Some(ref d) = self.decompressed {
+                let blob_list = parse_blob_list(Cursor::new(d), self.extended)
+                    .map_err(|err| self.corrupt(0, format!("invalid blob list: {}", err)))?;
+                self.blob_list = Some(blob_list);
             }
         }
 
         Ok(())
     }
 
-    fn get_blob<'b>(&'b self, idx: u32) -> Result<&'b [u8]> {
+    /// Reads all of `decoder` into a pooled buffer, reporting progress every `PROGRESS_STEP`
+    /// bytes. `total_in` gives the number of compressed bytes consumed, to locate errors.
+    #[cfg(any(not(feature = "wasm"), feature = "bzip2"))]
+    fn decode<D: Read>(
+        &self,
+        decoder: &mut D,
+        total_in: fn(&D) -> u64,
+        total: Option<usize>,
+        on_progress: &dyn Fn(usize, Option<usize>),
+    ) -> Result<PooledBuffer> {
+        let mut d = PooledBuffer::new(self.pool.clone());
+        d.data.reserve(self.view.len());
+        loop {
+            match decoder.take(PROGRESS_STEP).read_to_end(&mut d.data) {
+                Ok(0) => break,
+                Ok(_) => on_progress(d.len(), total),
+                Err(err) => {
+                    // skip the info byte, so the offset is relative to the cluster start
+                    return Err(self.corrupt(1 + total_in(decoder), err.to_string()));
+                }
+            }
+        }
+
+        Ok(d)
+    }
+
+    /// Decompresses all of `compressed` into `out`. Unlike `decode`, errors carry no offset,
+    /// as lzma-rs does not report how much input it consumed.
+    #[cfg(feature = "wasm")]
+    fn xz_decompress<R: Read, W: Write>(&self, compressed: R, out: &mut W) -> Result<()> {
+        lzma_rs::xz_decompress(&mut io::BufReader::new(compressed), out)
+            .map_err(|err| self.corrupt(1, err.to_string()))
+    }
+
+    fn blob_count(&self) -> u32 {
+        // the last offset marks the end of the last blob
+        match self.blob_list {
+            Some(ref list) => list.len().saturating_sub(1) as u32,
+            None => 0,
+        }
+    }
+
+    fn blob_range(&self, idx: u32) -> Result<(usize, usize)> {
         match self.blob_list {
             Some(ref list) => {
+                let blob_count = self.blob_count();
+                if idx >= blob_count {
+                    return Err(Error::InvalidBlobIndex {
+                        blob_idx: idx,
+                        blob_count,
+                    });
+                }
+
                 let start = list[idx as usize] as usize;
-                let n = idx as usize + 1;
-                let end = if list.len() > n {
-                    list[n] as usize
-                } else {
-                    self.size as usize
-                };
+                let end = list[idx as usize + 1] as usize;
 
-                Ok(match self.compression {
-                    Compression::LZMA2 => {
-                        // decompressed, so we know this exists
-                        &self.decompressed.as_ref().unwrap().as_slice()[start..end]
-                    }
-                    Compression::None => &self.view[1 + start..1 + end],
-                })
+                Ok((start, end))
             }
             None => Err(Error::MissingBlobList),
         }
     }
+
+    fn get_blob<'b>(&'b self, idx: u32) -> Result<&'b [u8]> {
+        let range = self.blob_bounds(idx)?;
+        Ok(&self.data()[range])
+    }
+
+    /// The decompressed data, the blob offsets followed by the blobs.
+    fn data(&self) -> &[u8] {
+        match self.compression {
+            Compression::None => &self.view[1..],
+            // decompressed, so we know this exists
+            _ => self.decompressed.as_ref().unwrap(),
+        }
+    }
+
+    /// Like `blob_range`, but also checks that the blob lies within `data`.
+    fn blob_bounds(&self, idx: u32) -> Result<Range<usize>> {
+        let (start, end) = self.blob_range(idx)?;
+        if start > end || end > self.data().len() {
+            return Err(self.corrupt(
+                start as u64,
+                format!("blob {} ends at {}, past the end of the cluster", idx, end),
+            ));
+        }
+
+        Ok(start..end)
+    }
+
+    fn corrupt(&self, byte_offset: u64, detail: String) -> Error {
+        Error::CorruptCluster {
+            cluster_idx: self.idx,
+            byte_offset,
+            detail,
+        }
+    }
+
+    fn blob_reader(&self, idx: u32) -> Result<Box<dyn Read + 'a>> {
+        match self.compression {
+            Compression::None => {
+                let range = self.blob_bounds(idx)?;
+                Ok(self.view.reader(1 + range.start, 1 + range.end))
+            }
+            compression => {
+                // stream through the compressed data, only reading the offsets we need
+                let compressed = self.view.reader(1, self.view.len());
+                let mut decoder: Box<dyn Read + 'a> = match compression {
+                    #[cfg(feature = "bzip2")]
+                    Compression::Bzip2 | Compression::Bzip2Old => {
+                        Box::new(BzDecoder::new(compressed))
+                    }
+                    #[cfg(not(feature = "bzip2"))]
+                    Compression::Bzip2 | Compression::Bzip2Old => {
+                        return Err(Error::UnsupportedCompression)
+                    }
+                    #[cfg(not(feature = "wasm"))]
+                    _ => Box::new(XzDecoder::new(compressed)),
+                    #[cfg(feature = "wasm")]
+                    _ => {
+                        let mut data = Vec::new();
+                        self.xz_decompress(compressed, &mut data)?;
+                        Box::new(Cursor::new(data))
+                    }
+                };
+                let offset_size = if self.extended { 8 } else { 4 };
+                let read_offset = |decoder: &mut Box<dyn Read + 'a>| -> Result<u64> {
+                    if self.extended {
+                        Ok(decoder.read_u64::<LittleEndian>()?)
+                    } else {
+                        Ok(decoder.read_u32::<LittleEndian>()? as u64)
+                    }
+                };
+
+                let first = read_offset(&mut decoder)?;
+                let blob_count = (first / offset_size).saturating_sub(1) as u32;
+                if idx >= blob_count {
+                    return Err(Error::InvalidBlobIndex {
+                        blob_idx: idx,
+                        blob_count,
+                    });
+                }
+
+                let mut offsets = vec![first];
+                while offsets.len() <= idx as usize + 1 {
+                    offsets.push(read_offset(&mut decoder)?);
+                }
+
+                let start = offsets[idx as usize];
+                let end = offsets[idx as usize + 1];
+                let consumed = offsets.len() as u64 * offset_size;
+                let skip = start.checked_sub(consumed).ok_or(Error::OutOfBounds)?;
+                let len = end.checked_sub(start).ok_or(Error::OutOfBounds)?;
+
+                io::copy(&mut (&mut decoder).take(skip), &mut io::sink())?;
+
+                Ok(Box::new(decoder.take(len)))
+            }
+        }
+    }
+}
+
+/// Assembles blobs into a new cluster, as used when writing ZIM files.
+///
+/// The offset size is chosen automatically: clusters whose offsets do not fit into 32 bits are
+/// written as extended clusters, which requires a major version 6 file.
+#[derive(Debug, Clone)]
+pub struct ClusterBuilder {
+    compression: Compression,
+    data: Vec<u8>,
+    starts: Vec<u64>, // offsets of the blobs into data
+}
+
+impl Default for ClusterBuilder {
+    fn default() -> Self {
+        ClusterBuilder::new()
+    }
+}
+
+impl ClusterBuilder {
+    /// Creates an empty, LZMA2 compressed cluster.
+    pub fn new() -> Self {
+        ClusterBuilder {
+            compression: Compression::LZMA2,
+            data: Vec::new(),
+            starts: Vec::new(),
+        }
+    }
+
+    pub fn set_compression(&mut self, compression: Compression) {
+        self.compression = compression;
+    }
+
+    /// Appends a blob, returning its index within the cluster.
+    pub fn add_blob(&mut self, data: &[u8]) -> u32 {
+        self.starts.push(self.data.len() as u64);
+        self.data.extend_from_slice(data);
+        (self.starts.len() - 1) as u32
+    }
+
+    /// Number of blobs added so far.
+    pub fn len(&self) -> usize {
+        self.starts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.starts.is_empty()
+    }
+
+    /// Sum of the blob sizes, before compression.
+    pub fn estimated_size(&self) -> u64 {
+        self.data.len() as u64
+    }
+
+    /// Whether the cluster needs 8 byte offsets.
+    pub fn is_extended(&self) -> bool {
+        // one offset per blob, plus the end offset
+        let list_size = (self.starts.len() as u64 + 1) * 4;
+        list_size + self.data.len() as u64 > u32::MAX as u64
+    }
+
+    /// Writes the info byte, the blob offsets and the blob data, returning the bytes written.
+    pub fn finish<W: Write>(self, mut writer: W) -> Result<u64> {
+        let extended = self.is_extended();
+        let offset_size = if extended { 8 } else { 4 };
+        let list_size = (self.starts.len() as u64 + 1) * offset_size;
+
+        let mut raw = Vec::with_capacity(list_size as usize + self.data.len());
+        // the offsets are relative to the start of the offset list, the last one marks the end
+        let end = self.data.len() as u64;
+        for offset in self.starts.iter().cloned().chain(Some(end)) {
+            if extended {
+                raw.write_u64::<LittleEndian>(list_size + offset)?;
+            } else {
+                raw.write_u32::<LittleEndian>((list_size + offset) as u32)?;
+            }
+        }
+        raw.extend_from_slice(&self.data);
+
+        let info = u8::from(self.compression) | if extended { 0x10 } else { 0 };
+        writer.write_all(&[info])?;
+
+        let written = match self.compression {
+            Compression::None => {
+                writer.write_all(&raw)?;
+                raw.len()
+            }
+            #[cfg(not(feature = "wasm"))]
+            Compression::LZMA2 => {
+                let mut encoder = XzEncoder::new(Vec::new(), 9);
+                encoder.write_all(&raw)?;
+                let compressed = encoder.finish()?;
+                writer.write_all(&compressed)?;
+                compressed.len()
+            }
+            #[cfg(feature = "wasm")]
+            Compression::LZMA2 => {
+                let mut compressed = Vec::new();
+                lzma_rs::xz_compress(&mut &raw[..], &mut compressed)?;
+                writer.write_all(&compressed)?;
+                compressed.len()
+            }
+            #[cfg(feature = "bzip2")]
+            Compression::Bzip2 | Compression::Bzip2Old => {
+                let mut encoder = BzEncoder::new(Vec::new(), bzip2::Compression::best());
+                encoder.write_all(&raw)?;
+                let compressed = encoder.finish()?;
+                writer.write_all(&compressed)?;
+                compressed.len()
+            }
+            #[cfg(not(feature = "bzip2"))]
+            Compression::Bzip2 | Compression::Bzip2Old => {
+                return Err(Error::UnsupportedCompression)
+            }
+        };
+
+        Ok(1 + written as u64)
+    }
+}
+
+/// Collects the output of lzma-rs, reporting progress every `PROGRESS_STEP` bytes.
+#[cfg(feature = "wasm")]
+struct ProgressWriter<'w> {
+    data: &'w mut Vec<u8>,
+    total: Option<usize>,
+    on_progress: &'w dyn Fn(usize, Option<usize>),
+}
+
+#[cfg(feature = "wasm")]
+impl<'w> Write for ProgressWriter<'w> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // only write up to the next step, the caller writes the rest
+        let to_step = PROGRESS_STEP as usize - self.data.len() % PROGRESS_STEP as usize;
+        let len = buf.len().min(to_step);
+        self.data.extend_from_slice(&buf[..len]);
+        if len == to_step {
+            (self.on_progress)(self.data.len(), self.total);
+        }
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
 }
 
 /// Parses the cluster information.
@@ -235,6 +1024,8 @@ impl<'a> InnerCluster<'a> {
 /// Fourth low bits:
 ///   - 0: default (no compression),
 ///   - 1: none (inherited from Zeno),
+///   - 2: bzip2 (inherited from Zeno),
+///   - 3: bzip2 (old zimlib),
 ///   - 4: LZMA2 compressed
 /// Firth bits :
 ///   - 0: normal (OFFSET_SIZE=4)
@@ -251,6 +1042,55 @@ fn parse_details(details: &u8) -> Result<(bool, Compression)> {
     Ok((reader.read_bool()?, Compression::from(reader.read_u8(4)?)?))
 }
 
+/// Reads the total uncompressed size from the index of an xz stream, without decoding it.
+///
+/// Returns `None` if `data` does not end with a well-formed stream footer and index.
+fn xz_uncompressed_size(data: &[u8]) -> Option<u64> {
+    // stream padding consists of null bytes, in multiples of four
+    let mut end = data.len();
+    while end >= 4 && data[end - 4..end] == [0, 0, 0, 0] {
+        end -= 4;
+    }
+
+    // stream footer: CRC32, backward size, stream flags and the magic bytes
+    let footer = data.get(end.checked_sub(12)?..end)?;
+    if &footer[10..12] != b"YZ" {
+        return None;
+    }
+    let mut cur = Cursor::new(&footer[4..8]);
+    let index_size = (cur.read_u32::<LittleEndian>().ok()? as usize + 1) * 4;
+    let index_start = (end - 12).checked_sub(index_size)?;
+    let index = &data[index_start..end - 12];
+
+    // index indicator, followed by the number of records
+    if index.first() != Some(&0) {
+        return None;
+    }
+    let mut pos = 1;
+    let mut read_int = || {
+        let mut value = 0u64;
+        for i in 0..9 {
+            let byte = *index.get(pos)?;
+            pos += 1;
+            value |= ((byte & 0x7f) as u64) << (i * 7);
+            if byte & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    };
+
+    let records = read_int()?;
+    let mut size = 0u64;
+    for _ in 0..records {
+        // each record is the unpadded size of the block followed by its uncompressed size
+        read_int()?;
+        size = size.checked_add(read_int()?)?;
+    }
+
+    Some(size)
+}
+
 fn parse_blob_list<T: ReadBytesExt>(mut cur: T, extended: bool) -> Result<Vec<u64>> {
     let mut blob_list = Vec::new();
 
@@ -261,11 +1101,14 @@ fn parse_blob_list<T: ReadBytesExt>(mut cur: T, extended: bool) -> Result<Vec<u6
         cur.read_u32::<LittleEndian>()? as u64
     };
 
+    // The first offset points right behind the offset list, so it also gives the number of
+    // offsets. A value below one offset (e.g. `first == 0`) means the cluster holds no blobs;
+    // only `first` is recorded in that case and the blob count is 0.
     let count = if extended { first / 8 } else { first / 4 };
 
     blob_list.push(first);
 
-    for _ in 0..(count as usize - 1) {
+    for _ in 1..count {
         if extended {
             blob_list.push(cur.read_u64::<LittleEndian>()?);
         } else {
@@ -275,3 +1118,333 @@ fn parse_blob_list<T: ReadBytesExt>(mut cur: T, extended: bool) -> Result<Vec<u6
 
     Ok(blob_list)
 }
+
+/// Writes a cluster with `blobs`, including the info byte.
+#[cfg(test)]
+fn write_cluster(blobs: &[&[u8]], compression: Compression) -> Vec<u8> {
+    let mut builder = ClusterBuilder::new();
+    builder.set_compression(compression);
+    for blob in blobs {
+        builder.add_blob(blob);
+    }
+    let mut raw = Vec::new();
+    builder.finish(&mut raw).unwrap();
+    raw
+}
+
+/// Opens the cluster of `write_cluster`, whose data is leaked so that the cluster can borrow it.
+#[cfg(test)]
+fn cluster_with_blobs<'a>(blobs: &[&[u8]], compression: Compression) -> Cluster<'a> {
+    let raw: &'a [u8] = Box::leak(write_cluster(blobs, compression).into_boxed_slice());
+    Cluster::new(raw, 0, 0, raw.len() as u64, 5).unwrap()
+}
+
+#[test]
+fn test_parse_blob_list_empty() {
+    for &extended in &[false, true] {
+        let raw = [0u8; 8];
+        let blob_list = parse_blob_list(Cursor::new(&raw[..]), extended).unwrap();
+        assert_eq!(blob_list, vec![0]);
+
+        // the single entry is the end offset, so there are no blobs
+        assert_eq!(blob_list.len().saturating_sub(1), 0);
+    }
+}
+
+#[cfg(test)]
+proptest::proptest! {
+    #[test]
+    fn test_parse_blob_list(extended: bool, sizes in proptest::collection::vec(0..1000u64, 0..50)) {
+        let width = if extended { 8 } else { 4 };
+        let mut offsets = vec![(sizes.len() as u64 + 1) * width];
+        for size in &sizes {
+            offsets.push(offsets[offsets.len() - 1] + size);
+        }
+
+        let mut raw = Vec::new();
+        for &offset in &offsets {
+            if extended {
+                raw.write_u64::<LittleEndian>(offset).unwrap();
+            } else {
+                raw.write_u32::<LittleEndian>(offset as u32).unwrap();
+            }
+        }
+        // the blobs follow the offsets
+        raw.extend_from_slice(&[0u8; 16]);
+
+        let blob_list = parse_blob_list(Cursor::new(&raw[..]), extended).unwrap();
+        proptest::prop_assert_eq!(&blob_list, &offsets);
+
+        let truncated = &raw[..offsets.len() * width as usize - 1];
+        proptest::prop_assert!(parse_blob_list(Cursor::new(truncated), extended).is_err());
+    }
+
+    #[test]
+    fn test_parse_blob_list_arbitrary(
+        extended: bool,
+        raw in proptest::collection::vec(proptest::num::u8::ANY, 0..64),
+    ) {
+        // must not panic, and may only read as many offsets as there are bytes
+        if let Ok(blob_list) = parse_blob_list(Cursor::new(&raw[..]), extended) {
+            let width = if extended { 8 } else { 4 };
+            proptest::prop_assert!(blob_list.len() * width <= raw.len());
+        }
+    }
+}
+
+#[test]
+fn test_parse_details() {
+    for details in 0..=255u8 {
+        let compression = details & 0x0f;
+        match parse_details(&details) {
+            Ok((extended, parsed)) => {
+                assert_eq!(extended, details & 0x10 != 0);
+                assert_eq!(parsed, Compression::from(compression).unwrap());
+                assert!(compression <= 4);
+            }
+            Err(Error::UnknownCompression) => assert!(compression > 4),
+            Err(err) => panic!("unexpected error for {:#04x}: {}", details, err),
+        }
+    }
+}
+
+#[test]
+fn test_cluster_builder() {
+    for &compression in &[Compression::None, Compression::LZMA2] {
+        let mut builder = ClusterBuilder::new();
+        builder.set_compression(compression);
+        assert_eq!(builder.add_blob(b"hello"), 0);
+        assert_eq!(builder.add_blob(b""), 1);
+        assert_eq!(builder.add_blob(b"world"), 2);
+        assert_eq!(builder.len(), 3);
+        assert_eq!(builder.estimated_size(), 10);
+        assert!(!builder.is_extended());
+
+        let mut raw = Vec::new();
+        let written = builder.finish(&mut raw).unwrap();
+        assert_eq!(written, raw.len() as u64);
+        assert_eq!(raw, write_cluster(&[b"hello", b"", b"world"], compression));
+
+        let cluster = cluster_with_blobs(&[b"hello", b"", b"world"], compression);
+        assert_eq!(cluster.blob_count().unwrap(), 3);
+        assert_eq!(&cluster.get_blob(0).unwrap()[..], b"hello");
+        assert_eq!(&cluster.get_blob(1).unwrap()[..], b"");
+        assert_eq!(&cluster.get_blob(2).unwrap()[..], b"world");
+        assert_eq!(cluster.map_blob(2, |blob| blob.to_vec()).unwrap(), b"world");
+        assert!(cluster.map_blob(3, |blob| blob.len()).is_err());
+    }
+}
+
+#[test]
+fn test_iter_blobs() {
+    for &compression in &[Compression::None, Compression::LZMA2] {
+        let cluster = cluster_with_blobs(&[b"hello", b"", b"world"], compression);
+
+        let mut blobs = cluster.iter_blobs();
+        assert_eq!(blobs.len(), 3);
+        let hello = blobs.next().unwrap().unwrap();
+        assert_eq!(blobs.len(), 2);
+        let rest: Vec<Vec<u8>> = blobs.map(|blob| blob.unwrap().to_vec()).collect();
+        assert_eq!(&hello[..], b"hello");
+        assert_eq!(rest, [&b""[..], &b"world"[..]]);
+
+        // the blobs are still readable while the cluster is locked by the first one
+        assert_eq!(cluster.map_blob(2, |blob| blob.to_vec()).unwrap(), b"world");
+        drop(hello);
+        cluster.drop_decompressed();
+    }
+
+    // a cluster which fails to decompress yields its error once
+    let raw = [Compression::LZMA2 as u8, 1, 2, 3];
+    let cluster = Cluster::new(&raw, 0, 0, raw.len() as u64, 5).unwrap();
+    let mut blobs = cluster.iter_blobs();
+    assert_eq!(blobs.len(), 1);
+    assert!(blobs.next().unwrap().is_err());
+    assert!(blobs.next().is_none());
+}
+
+#[test]
+fn test_decompressed_size() {
+    for &compression in &[Compression::None, Compression::LZMA2] {
+        let mut raw = write_cluster(&[&[7u8; 1000], b"blob"], compression);
+        // stream padding after the xz stream is ignored
+        if compression == Compression::LZMA2 {
+            raw.extend_from_slice(&[0u8; 8]);
+        }
+
+        let cluster = Cluster::new(&raw, 0, 0, raw.len() as u64, 5).unwrap();
+        assert_eq!(cluster.compressed_size(), raw.len() as u64 - 1);
+        // three offsets followed by the blobs
+        assert_eq!(cluster.decompressed_size().unwrap(), 3 * 4 + 1004);
+        // the size came from the xz index, not from decompressing
+        assert!(cluster.0.read().unwrap().decompressed.is_none());
+    }
+}
+
+#[test]
+fn test_decompress_pool() {
+    let raw = write_cluster(&[b"pooled"], Compression::LZMA2);
+
+    let pool = Arc::new(Mutex::new(DecompressPool::new()));
+    let end = raw.len() as u64;
+    let cluster = Cluster::new_with_pool(&raw, 0, 0, end, 5, pool.clone()).unwrap();
+    assert_eq!(
+        cluster.map_blob(0, |blob| blob.to_vec()).unwrap(),
+        b"pooled"
+    );
+    assert!(pool.lock().unwrap().is_empty());
+
+    // the buffer goes back to the pool, and is handed out again
+    drop(cluster);
+    assert_eq!(pool.lock().unwrap().len(), 1);
+    let cluster = Cluster::new_with_pool(&raw, 0, 0, end, 5, pool.clone()).unwrap();
+    cluster.decompress().unwrap();
+    assert!(pool.lock().unwrap().is_empty());
+    assert_eq!(&cluster.get_blob(0).unwrap()[..], b"pooled");
+}
+
+#[test]
+fn test_decompress_with_progress() {
+    use std::cell::RefCell;
+
+    let cluster = cluster_with_blobs(&[&vec![1u8; 200 * 1024]], Compression::LZMA2);
+    let reports = RefCell::new(Vec::new());
+    cluster
+        .decompress_with_progress(|done, total| reports.borrow_mut().push((done, total)))
+        .unwrap();
+
+    let reports = reports.into_inner();
+    let size = 2 * 4 + 200 * 1024;
+    assert_eq!(reports.len(), 4);
+    assert!(reports.iter().all(|&(_, total)| total == Some(size)));
+    assert_eq!(reports.last(), Some(&(size, Some(size))));
+}
+
+#[test]
+fn test_bzip2() {
+    // info byte for bzip2, followed by data which is never looked at without the feature
+    let raw = [Compression::Bzip2 as u8, 0, 0, 0, 0];
+    assert_eq!(Compression::from(raw[0]).unwrap(), Compression::Bzip2);
+    assert_eq!(Compression::from(3).unwrap(), Compression::Bzip2Old);
+
+    #[cfg(not(feature = "bzip2"))]
+    {
+        let cluster = Cluster::new(&raw, 0, 0, raw.len() as u64, 5).unwrap();
+        match cluster.map_blob(0, |blob| blob.len()) {
+            Err(Error::UnsupportedCompression) => {}
+            other => panic!("expected UnsupportedCompression, got {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "bzip2")]
+    for &compression in &[Compression::Bzip2, Compression::Bzip2Old] {
+        let cluster = cluster_with_blobs(&[b"hello", b"world"], compression);
+        assert_eq!(cluster.compression(), compression);
+        assert_eq!(cluster.map_blob(1, |blob| blob.to_vec()).unwrap(), b"world");
+
+        let mut data = Vec::new();
+        cluster
+            .blob_reader(0)
+            .unwrap()
+            .read_to_end(&mut data)
+            .unwrap();
+        assert_eq!(data, b"hello");
+    }
+}
+
+#[test]
+fn test_blob_reader_truncated() {
+    let mut raw = write_cluster(&[b"hello", b"world"], Compression::None);
+    raw.truncate(raw.len() - 3);
+    let cluster = Cluster::new(&raw, 0, 0, raw.len() as u64, 5).unwrap();
+
+    let mut data = Vec::new();
+    cluster
+        .blob_reader(0)
+        .unwrap()
+        .read_to_end(&mut data)
+        .unwrap();
+    assert_eq!(data, b"hello");
+    assert!(matches!(
+        cluster.blob_reader(1),
+        Err(Error::CorruptCluster { cluster_idx: 0, .. })
+    ));
+}
+
+#[test]
+fn test_read_blob() {
+    use std::io::BufRead;
+
+    let cluster = cluster_with_blobs(&[b"first line\nsecond line"], Compression::LZMA2);
+
+    let mut blob = cluster.get_blob(0).unwrap();
+    assert_eq!(blob.len(), 22);
+    let mut line = String::new();
+    blob.read_line(&mut line).unwrap();
+    assert_eq!(line, "first line\n");
+
+    let mut rest = Vec::new();
+    io::copy(&mut blob, &mut rest).unwrap();
+    assert_eq!(rest, b"second line");
+    assert_eq!(blob.read(&mut [0u8; 4]).unwrap(), 0);
+    // reading does not change the data the blob derefs to
+    assert_eq!(&blob[..5], b"first");
+}
+
+#[test]
+fn test_drop_decompressed() {
+    for &compression in &[Compression::None, Compression::LZMA2] {
+        let cluster = cluster_with_blobs(&[b"hello"], compression);
+
+        assert_eq!(&cluster.get_blob(0).unwrap()[..], b"hello");
+        cluster.drop_decompressed();
+        assert!(cluster.0.read().unwrap().decompressed.is_none());
+        let compressed = compression != Compression::None;
+        assert_eq!(cluster.0.read().unwrap().needs_decompression(), compressed);
+        assert_eq!(&cluster.get_blob(0).unwrap()[..], b"hello");
+    }
+}
+
+#[test]
+fn test_get_blobs() {
+    for &compression in &[Compression::None, Compression::LZMA2] {
+        let cluster = cluster_with_blobs(&[b"zero", b"one"], compression);
+
+        let blobs: Vec<_> = cluster
+            .get_blobs(&[1, 3, 0])
+            .unwrap()
+            .into_iter()
+            .map(|blob| match blob {
+                Ok(blob) => Ok(blob.to_vec()),
+                Err(Error::InvalidBlobIndex { blob_idx, .. }) => Err(blob_idx),
+                Err(err) => panic!("unexpected error {}", err),
+            })
+            .collect();
+        assert_eq!(blobs, [Ok(b"one".to_vec()), Err(3), Ok(b"zero".to_vec())]);
+    }
+}
+
+#[test]
+fn test_into_blob() {
+    let raw = write_cluster(&[b"zero", b"one"], Compression::None);
+    let cluster = Cluster::new(&raw, 0, 0, raw.len() as u64, 5).unwrap();
+    let blob = cluster.into_blob(1).unwrap();
+    assert_eq!(&blob[..], b"one");
+    // blobs of uncompressed clusters point into the file
+    assert!(raw.as_ptr_range().contains(&blob.as_ptr()));
+
+    let cluster = cluster_with_blobs(&[b"zero", b"one"], Compression::LZMA2);
+    assert_eq!(&cluster.into_blob(0).unwrap()[..], b"zero");
+
+    // a shared cluster is left intact
+    let cluster = cluster_with_blobs(&[b"zero", b"one"], Compression::LZMA2);
+    let clone = cluster.clone();
+    assert_eq!(&cluster.into_blob(1).unwrap()[..], b"one");
+    assert_eq!(&clone.get_blob(0).unwrap()[..], b"zero");
+
+    let cluster = cluster_with_blobs(&[b"zero", b"one"], Compression::LZMA2);
+    match cluster.into_blob(2) {
+        Err(Error::InvalidBlobIndex { blob_idx, .. }) => assert_eq!(blob_idx, 2),
+        other => panic!("unexpected result {:?}", other.map(|blob| blob.to_vec())),
+    }
+}
@@ -1,7 +1,8 @@
 use std;
 use std::convert::TryFrom;
-use std::io::BufRead;
-use std::io::Cursor;
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, Cursor};
+use std::path::PathBuf;
 
 use byteorder::{LittleEndian, ReadBytesExt};
 
@@ -22,15 +23,48 @@ pub struct DirectoryEntry {
     /// updates or revisions in the original history
     pub revision: Option<u32>,
     /// the URL as refered in the URL pointer list
+    ///
+    /// This may be empty, e.g. for deleted entries or in files written by some older tools. It
+    /// is returned as stored, as the URL pointer list is sorted by it.
     pub url: String,
     /// title as refered in the Title pointer list or empty; in case it is empty,
     /// the URL is used as title
     pub title: String,
     pub target: Option<Target>,
+    /// the index in the URL pointer list, if the entry was read through it
+    pub url_index: Option<u32>,
+}
+
+/// Entries are equal if they have the same namespace and URL, as the same article can appear
+/// with different metadata, e.g. in two versions of a file.
+impl PartialEq for DirectoryEntry {
+    fn eq(&self, other: &DirectoryEntry) -> bool {
+        self.namespace == other.namespace && self.url == other.url
+    }
+}
+
+impl Eq for DirectoryEntry {}
+
+impl Hash for DirectoryEntry {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.namespace.hash(state);
+        self.url.hash(state);
+    }
 }
 
 impl DirectoryEntry {
+    /// Parses the directory entry at the start of `s`, failing if the URL or title are not
+    /// valid UTF-8.
     pub fn new(zim: &Zim, s: &[u8]) -> Result<DirectoryEntry> {
+        DirectoryEntry::parse(zim, s, false)
+    }
+
+    /// Like `new`, but replaces invalid UTF-8 sequences in the URL and title with U+FFFD.
+    pub fn new_lossy(zim: &Zim, s: &[u8]) -> Result<DirectoryEntry> {
+        DirectoryEntry::parse(zim, s, true)
+    }
+
+    fn parse(zim: &Zim, s: &[u8], lossy: bool) -> Result<DirectoryEntry> {
         let mut cur = Cursor::new(s);
         let mime_id = cur.read_u16::<LittleEndian>()?;
         let mime_type = zim.get_mimetype(mime_id).ok_or(Error::UnknownMimeType)?;
@@ -49,18 +83,8 @@ impl DirectoryEntry {
             Some(Target::Cluster(cluster_number, blob_number))
         };
 
-        let url = {
-            let mut vec = Vec::new();
-            let size = cur.read_until(0, &mut vec)?;
-            vec.truncate(size - 1);
-            String::from_utf8(vec)?
-        };
-        let title = {
-            let mut vec = Vec::new();
-            let size = cur.read_until(0, &mut vec)?;
-            vec.truncate(size - 1);
-            String::from_utf8(vec)?
-        };
+        let url = read_string(&mut cur, lossy)?;
+        let title = read_string(&mut cur, lossy)?;
 
         Ok(DirectoryEntry {
             mime_type: mime_type,
@@ -69,6 +93,208 @@ impl DirectoryEntry {
             url: url,
             title: title,
             target: target,
+            url_index: None,
         })
     }
+
+    pub fn is_redirect(&self) -> bool {
+        self.mime_type == MimeType::Redirect
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.mime_type == MimeType::DeletedEntry
+    }
+
+    pub fn is_link_target(&self) -> bool {
+        self.mime_type == MimeType::LinkTarget
+    }
+
+    /// Whether this entry has a real MIME type, i.e. is none of the special entries above.
+    pub fn is_article(&self) -> bool {
+        matches!(self.mime_type, MimeType::Type(_))
+    }
+
+    /// Whether this entry points to a blob holding its content.
+    pub fn has_data(&self) -> bool {
+        self.target.is_some() && !self.is_redirect()
+    }
+
+    /// The position of this entry in the file, found through its URL index.
+    ///
+    /// Returns `None` for entries which weren't read through the URL pointer list of `zim`.
+    pub fn byte_offset_in_file(&self, zim: &Zim) -> Option<u64> {
+        zim.url_list.get(self.url_index? as usize).cloned()
+    }
+
+    /// The URL prefixed with the namespace character, like `A/Main_Page`.
+    pub fn full_url(&self) -> String {
+        format!("{}/{}", self.namespace, self.url)
+    }
+
+    /// The relative path this entry is extracted to: the namespace directory, followed by the
+    /// URL with a file extension matching the MIME type, if it is missing.
+    pub fn as_path(&self) -> PathBuf {
+        // make absolute urls relative to the namespace folder
+        let url = self.url.strip_prefix('/').unwrap_or(&self.url);
+        let mut path = PathBuf::from(self.namespace.to_string()).join(url);
+
+        if let Some(extension) = self.mime_type.extension() {
+            if path.extension().is_none()
+                || !path
+                    .extension()
+                    .unwrap()
+                    .to_str()
+                    .unwrap_or_default()
+                    .starts_with(extension)
+            {
+                path.set_extension(extension);
+            }
+        }
+
+        path
+    }
+}
+
+/// Reads a zero terminated string, failing if the input ends before the terminator.
+fn read_string(cur: &mut Cursor<&[u8]>, lossy: bool) -> Result<String> {
+    let mut vec = Vec::new();
+    cur.read_until(0, &mut vec)?;
+    if vec.pop() != Some(0) {
+        return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "unterminated string").into());
+    }
+    if lossy {
+        Ok(String::from_utf8_lossy(&vec).into_owned())
+    } else {
+        Ok(String::from_utf8(vec)?)
+    }
+}
+
+#[test]
+fn test_full_url_and_path() {
+    let entry = DirectoryEntry {
+        mime_type: MimeType::Type("text/html".into()),
+        namespace: Namespace::Articles,
+        revision: None,
+        url: "/Main_Page".into(),
+        title: String::new(),
+        target: None,
+        url_index: None,
+    };
+
+    assert_eq!(entry.full_url(), "A//Main_Page");
+    assert_eq!(entry.as_path(), PathBuf::from("A/Main_Page.html"));
+
+    let entry = DirectoryEntry {
+        mime_type: MimeType::Redirect,
+        url: "style.css".into(),
+        ..entry
+    };
+    assert_eq!(entry.full_url(), "A/style.css");
+    assert_eq!(entry.as_path(), PathBuf::from("A/style.css"));
+}
+
+#[test]
+fn test_empty_url() {
+    let zim = crate::zim::zim_with_articles(&[(Namespace::Articles, "", "Untitled")]);
+
+    let entry = zim.get_by_url_index(0).unwrap();
+    assert_eq!(entry.url, "");
+    assert_eq!(entry.title, "Untitled");
+    assert_eq!(entry.full_url(), "A/");
+}
+
+#[test]
+fn test_byte_offset_in_file() {
+    let zim = crate::zim::zim_with_articles(&[
+        (Namespace::Articles, "Apple", ""),
+        (Namespace::Articles, "Banana", ""),
+    ]);
+
+    for (idx, entry) in zim.iterate_by_urls().enumerate() {
+        let offset = entry.byte_offset_in_file(&zim).unwrap();
+        assert_eq!(offset, zim.url_list[idx]);
+        let parsed = DirectoryEntry::new(&zim, &zim.master_view[offset as usize..]).unwrap();
+        assert_eq!(parsed, entry);
+        assert_eq!(parsed.byte_offset_in_file(&zim), None);
+    }
+}
+
+#[test]
+fn test_eq_and_hash() {
+    use std::collections::HashSet;
+
+    let entry = |namespace, mime_type, title: &str, target| DirectoryEntry {
+        mime_type,
+        namespace,
+        revision: None,
+        url: "Main_Page".into(),
+        title: title.into(),
+        target: Some(target),
+        url_index: None,
+    };
+    let article = entry(
+        Namespace::Articles,
+        MimeType::Type("text/html".into()),
+        "Main Page",
+        Target::Cluster(0, 0),
+    );
+    let redirect = entry(
+        Namespace::Articles,
+        MimeType::Redirect,
+        "Main page",
+        Target::Redirect(1),
+    );
+    let image = entry(
+        Namespace::ImagesFile,
+        MimeType::Redirect,
+        "Main page",
+        Target::Redirect(1),
+    );
+    assert_eq!(article, redirect);
+    assert_ne!(article, image);
+
+    let entries: HashSet<_> = vec![article, redirect, image].into_iter().collect();
+    assert_eq!(entries.len(), 2);
+}
+
+#[cfg(test)]
+proptest::proptest! {
+    #[test]
+    fn test_truncated_entry(
+        redirect: bool,
+        url in "[^\\x00]{0,20}",
+        title in "[^\\x00]{0,20}",
+        len_fraction in 0.0..1.0f64,
+    ) {
+        use std::io::Cursor;
+
+        use crate::writer::ZimWriter;
+
+        let mut writer = ZimWriter::new();
+        writer
+            .add_article(Namespace::Articles, "target", "", "text/html", b"")
+            .unwrap();
+        if redirect {
+            writer
+                .add_redirect(Namespace::ImagesFile, &url, &title, "target", Namespace::Articles)
+                .unwrap();
+        } else {
+            writer
+                .add_article(Namespace::ImagesFile, &url, &title, "text/html", b"")
+                .unwrap();
+        }
+        let mut raw = Vec::new();
+        writer.finalize(&mut raw).unwrap();
+        let zim = Zim::new_from_reader(Cursor::new(raw)).unwrap();
+
+        // the entry in namespace I comes last in the URL pointer list
+        let start = zim.url_list[1] as usize;
+        let fixed = if redirect { 12 } else { 16 };
+        let end = start + fixed + url.len() + title.len() + 2;
+        let entry = &zim.master_view[start..end];
+        proptest::prop_assert!(DirectoryEntry::new(&zim, entry).is_ok());
+
+        let len = (entry.len() as f64 * len_fraction) as usize;
+        proptest::prop_assert!(DirectoryEntry::new(&zim, &entry[..len]).is_err());
+    }
 }
@@ -1,21 +1,64 @@
 use crate::directory_entry::DirectoryEntry;
 use std;
+use std::iter::FusedIterator;
 
+use rayon::iter::plumbing::{bridge, Consumer, Producer, ProducerCallback, UnindexedConsumer};
+use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
+
+use crate::errors::Result;
 use crate::zim::Zim;
 
+/// Iterates over the directory entries sorted by URL, skipping entries that fail to parse.
+///
+/// As broken entries are only found while iterating, the number of entries left is not known
+/// in advance; `size_hint` gives the number of URL indices left as the upper bound.
 pub struct DirectoryIterator<'a> {
-    max: u32,
     next: u32,
+    back: u32,
+    skipped: u32,
     zim: &'a Zim,
 }
 
 impl<'a> DirectoryIterator<'a> {
     pub fn new(zim: &'a Zim) -> DirectoryIterator<'a> {
+        DirectoryIterator::with_range(zim, 0, zim.header.article_count)
+    }
+
+    /// Starts at URL index `start`, e.g. to resume after the last entry processed before.
+    pub fn from_index(zim: &'a Zim, start: u32) -> DirectoryIterator<'a> {
+        let end = zim.header.article_count;
+        DirectoryIterator::with_range(zim, start.min(end), end)
+    }
+
+    /// Moves the front of the iterator to URL index `idx`, without parsing the entries before.
+    ///
+    /// `idx` is clamped to the back of the iterator, so moving past it ends the iteration.
+    pub fn skip_to(&mut self, idx: u32) -> &mut Self {
+        self.next = idx.min(self.back);
+        self
+    }
+
+    /// The number of entries skipped so far because they failed to parse.
+    pub fn skipped_count(&self) -> u32 {
+        self.skipped
+    }
+
+    /// Iterates over the URL indices `start..end` only.
+    fn with_range(zim: &'a Zim, start: u32, end: u32) -> DirectoryIterator<'a> {
         DirectoryIterator {
-            max: zim.header.article_count,
-            next: 0,
-            zim: zim,
+            next: start,
+            back: end,
+            skipped: 0,
+            zim,
+        }
+    }
+
+    fn entry_at(&mut self, idx: u32) -> Option<DirectoryEntry> {
+        let entry = self.zim.get_by_url_index(idx).ok();
+        if entry.is_none() {
+            self.skipped += 1;
         }
+        entry
     }
 }
 
@@ -23,21 +66,247 @@ impl<'a> std::iter::Iterator for DirectoryIterator<'a> {
     type Item = DirectoryEntry;
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.next >= self.max {
+        while self.next < self.back {
+            let idx = self.next;
+            self.next += 1;
+
+            if let Some(entry) = self.entry_at(idx) {
+                return Some(entry);
+            }
+        }
+
+        None
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (0, Some((self.back - self.next) as usize))
+    }
+}
+
+impl<'a> DoubleEndedIterator for DirectoryIterator<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        // `back` is exclusive, so once it meets `next` both ends are exhausted
+        while self.next < self.back {
+            self.back -= 1;
+
+            if let Some(entry) = self.entry_at(self.back) {
+                return Some(entry);
+            }
+        }
+
+        None
+    }
+}
+
+impl<'a> FusedIterator for DirectoryIterator<'a> {}
+
+/// Parallel iterator over all directory entries, sorted by URL.
+///
+/// Created by calling `par_iter()` (or `into_par_iter()`) on a `&Zim`. Splitting happens on URL
+/// indices, so no entry is parsed more than once. Every URL index yields the parsed entry or the
+/// error reading it, like `Zim::try_iterate_by_urls`, so the length is always `article_count`.
+pub struct ZimParIter<'a> {
+    zim: &'a Zim,
+}
+
+impl<'a> IntoParallelIterator for &'a Zim {
+    type Iter = ZimParIter<'a>;
+    type Item = Result<DirectoryEntry>;
+
+    fn into_par_iter(self) -> Self::Iter {
+        ZimParIter { zim: self }
+    }
+}
+
+impl<'a> ParallelIterator for ZimParIter<'a> {
+    type Item = Result<DirectoryEntry>;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+    where
+        C: UnindexedConsumer<Self::Item>,
+    {
+        bridge(self, consumer)
+    }
+
+    fn opt_len(&self) -> Option<usize> {
+        Some(self.len())
+    }
+}
+
+impl<'a> IndexedParallelIterator for ZimParIter<'a> {
+    fn len(&self) -> usize {
+        self.zim.header.article_count as usize
+    }
+
+    fn drive<C: Consumer<Self::Item>>(self, consumer: C) -> C::Result {
+        bridge(self, consumer)
+    }
+
+    fn with_producer<CB: ProducerCallback<Self::Item>>(self, callback: CB) -> CB::Output {
+        callback.callback(DirectoryProducer {
+            zim: self.zim,
+            start: 0,
+            end: self.zim.header.article_count,
+        })
+    }
+}
+
+struct DirectoryProducer<'a> {
+    zim: &'a Zim,
+    start: u32,
+    end: u32,
+}
+
+impl<'a> Producer for DirectoryProducer<'a> {
+    type Item = Result<DirectoryEntry>;
+    type IntoIter = EntryResults<'a>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        EntryResults {
+            next: self.start,
+            back: self.end,
+            zim: self.zim,
+        }
+    }
+
+    fn split_at(self, index: usize) -> (Self, Self) {
+        let mid = self.start + index as u32;
+        (
+            DirectoryProducer {
+                zim: self.zim,
+                start: self.start,
+                end: mid,
+            },
+            DirectoryProducer {
+                zim: self.zim,
+                start: mid,
+                end: self.end,
+            },
+        )
+    }
+}
+
+/// The entries at the URL indices `next..back`, including the ones which fail to parse.
+struct EntryResults<'a> {
+    next: u32,
+    back: u32,
+    zim: &'a Zim,
+}
+
+impl<'a> Iterator for EntryResults<'a> {
+    type Item = Result<DirectoryEntry>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next >= self.back {
             return None;
         }
 
-        let dir_entry_ptr = self.zim.url_list[self.next as usize] as usize;
         self.next += 1;
+        Some(self.zim.get_by_url_index(self.next - 1))
+    }
 
-        let len = self.zim.master_view.len();
-        let slice = self
-            .zim
-            .master_view
-            .get(dir_entry_ptr..(len - dir_entry_ptr));
-        match slice {
-            Some(slice) => DirectoryEntry::new(self.zim, slice).ok(),
-            None => None,
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.back - self.next) as usize;
+        (len, Some(len))
+    }
+}
+
+impl<'a> DoubleEndedIterator for EntryResults<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.next >= self.back {
+            return None;
         }
+
+        self.back -= 1;
+        Some(self.zim.get_by_url_index(self.back))
     }
 }
+
+impl<'a> ExactSizeIterator for EntryResults<'a> {}
+
+#[test]
+fn test_skip_to() {
+    use crate::namespace::Namespace;
+
+    let zim = crate::zim::zim_with_articles(&[
+        (Namespace::Articles, "Apple", ""),
+        (Namespace::Articles, "Banana", ""),
+        (Namespace::Articles, "Cherry", ""),
+        (Namespace::Articles, "Date", ""),
+    ]);
+
+    let urls =
+        |iter: &mut DirectoryIterator| -> Vec<String> { iter.map(|entry| entry.url).collect() };
+    assert_eq!(urls(zim.iterate_by_urls().skip_to(2)), ["Cherry", "Date"]);
+    assert_eq!(urls(&mut DirectoryIterator::from_index(&zim, 3)), ["Date"]);
+    assert!(urls(&mut DirectoryIterator::from_index(&zim, 10)).is_empty());
+
+    let mut iter = zim.iterate_by_urls();
+    iter.next_back();
+    assert!(iter.skip_to(10).next().is_none());
+}
+
+#[test]
+fn test_double_ended() {
+    use crate::namespace::Namespace;
+
+    let zim = crate::zim::zim_with_articles(&[
+        (Namespace::Articles, "Apple", ""),
+        (Namespace::Articles, "Banana", ""),
+        (Namespace::Articles, "Cherry", ""),
+    ]);
+
+    assert_eq!(zim.iterate_by_urls().size_hint(), (0, Some(3)));
+    let reversed: Vec<String> = zim.iterate_by_urls().rev().map(|entry| entry.url).collect();
+    assert_eq!(reversed, ["Cherry", "Banana", "Apple"]);
+    assert_eq!(zim.iterate_by_urls().last().unwrap().url, "Cherry");
+
+    let mut iter = zim.iterate_by_urls();
+    assert_eq!(iter.next().unwrap().url, "Apple");
+    assert_eq!(iter.next_back().unwrap().url, "Cherry");
+    assert_eq!(iter.size_hint(), (0, Some(1)));
+    assert_eq!(iter.next().unwrap().url, "Banana");
+    assert!(iter.next().is_none());
+    assert!(iter.next_back().is_none());
+    assert!(iter.next().is_none());
+}
+
+#[test]
+fn test_skip_broken_entries() {
+    let zim = crate::zim::zim_with_broken_entry();
+
+    let mut iter = zim.iterate_by_urls();
+    assert_eq!(iter.size_hint(), (0, Some(3)));
+    let urls: Vec<String> = iter.by_ref().map(|entry| entry.url).collect();
+    assert_eq!(urls, ["Apple", "Cherry"]);
+    assert_eq!(iter.skipped_count(), 1);
+
+    let mut iter = zim.iterate_by_urls();
+    assert_eq!(iter.next_back().unwrap().url, "Cherry");
+    assert_eq!(iter.next_back().unwrap().url, "Apple");
+    assert!(iter.next_back().is_none());
+    assert_eq!(iter.skipped_count(), 1);
+}
+
+#[test]
+fn test_par_iter_broken_entry() {
+    use rayon::prelude::*;
+
+    use crate::errors::Error;
+
+    let zim = crate::zim::zim_with_broken_entry();
+
+    // one item per URL index, so indexed adaptors stay aligned
+    let entries: Vec<_> = zim.par_iter().collect();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].as_ref().unwrap().url, "Apple");
+    assert!(matches!(entries[1], Err(Error::Utf8(_))));
+    assert_eq!(entries[2].as_ref().unwrap().url, "Cherry");
+
+    let indices: Vec<usize> = zim
+        .par_iter()
+        .enumerate()
+        .filter_map(|(idx, entry)| entry.ok().map(|_| idx))
+        .collect();
+    assert_eq!(indices, [0, 2]);
+}
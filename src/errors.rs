@@ -1,72 +1,86 @@
 use bitreader;
 use std;
-use std::fmt;
+use thiserror::Error;
+
+use crate::namespace::Namespace;
 
 pub type Result<T> = std::result::Result<T, Error>;
 
-#[derive(Debug)]
+#[derive(Debug, Error)]
 pub enum Error {
+    #[error("unknown compression")]
     UnknownCompression,
+    #[error("unsupported compression, enable the bzip2 feature")]
+    UnsupportedCompression,
+    #[error("unknown mimetype")]
     UnknownMimeType,
-    InvalidMagicNumber,
+    #[error("invalid magic number: found 0x{found:08X}, expected 0x{expected:08X}")]
+    InvalidMagicNumber { found: u32, expected: u32 },
+    #[error("invalid major version, must be 5 or 6")]
     InvalidVersion,
+    #[error("invalid header")]
     InvalidHeader,
-    InvalidNamespace,
+    /// A position in the header points to a place where it can't be, e.g. into the header.
+    #[error("invalid header pointer: {field}")]
+    InvalidHeaderPointer { field: &'static str },
+    #[error("invalid namespace: {0:#04x}")]
+    InvalidNamespace(u8),
+    /// Text which is not a single ASCII character was given where a namespace was expected.
+    #[error("invalid namespace: {0:?}")]
+    InvalidNamespaceStr(String),
+    #[error("cluster extension requires major version 6")]
     InvalidClusterExtension,
+    #[error("cluster is missing a blob list")]
     MissingBlobList,
+    #[error("invalid blob index {blob_idx}, cluster has {blob_count} blobs")]
+    InvalidBlobIndex { blob_idx: u32, blob_count: u32 },
+    #[error("invalid entry index {idx}, file has {max} entries")]
+    InvalidEntryIndex { idx: u32, max: u32 },
+    #[error("invalid cluster index {idx}, file has {max} clusters")]
+    InvalidClusterIndex { idx: u32, max: u32 },
+    #[error("missing checksum")]
     MissingChecksum,
+    #[error("invalid checksum")]
     InvalidChecksum,
+    #[error("unsupported checksum type, enable the sha2 feature")]
+    UnsupportedChecksum,
+    #[error("out of bounds access")]
     OutOfBounds,
-    ParsingError(Box<dyn std::error::Error + Send + Sync>),
-}
-
-impl fmt::Display for Error {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        f.write_str(std::error::Error::description(self))
-    }
-}
-
-impl std::error::Error for Error {
-    fn description(&self) -> &str {
-        match *self {
-            Error::UnknownCompression => "unknown compression",
-            Error::UnknownMimeType => "unknown mimetype",
-            Error::InvalidMagicNumber => "invalid magic number",
-            Error::InvalidVersion => "invalid major version, must be 5 or 6",
-            Error::InvalidHeader => "invalid header",
-            Error::InvalidClusterExtension => "cluster extension requires major version 6",
-            Error::MissingBlobList => "cluster is missing a blob list",
-            Error::OutOfBounds => "out of bounds access",
-            Error::ParsingError(_) => "failed to parse",
-            Error::InvalidNamespace => "invalid namespace",
-            Error::MissingChecksum => "missing checksum",
-            Error::InvalidChecksum => "invalid checksum",
-        }
-    }
-
-    #[inline]
-    fn cause(&self) -> Option<&dyn std::error::Error> {
-        match *self {
-            Error::ParsingError(ref err) => Some(&**err),
-            _ => None,
-        }
-    }
-}
-
-impl From<std::string::FromUtf8Error> for Error {
-    fn from(err: std::string::FromUtf8Error) -> Error {
-        Error::ParsingError(err.into())
-    }
-}
-
-impl From<std::io::Error> for Error {
-    fn from(err: std::io::Error) -> Error {
-        Error::ParsingError(err.into())
-    }
-}
-
-impl From<bitreader::BitReaderError> for Error {
-    fn from(err: bitreader::BitReaderError) -> Error {
-        Error::ParsingError(err.into())
-    }
+    /// The file ends before a region referenced by the header or a pointer list.
+    #[error("truncated file: expected at least {expected_end} bytes, found {actual_size}")]
+    TruncatedFile { expected_end: u64, actual_size: u64 },
+    /// A cluster could not be decompressed or read. `byte_offset` is relative to the start of
+    /// the cluster for decompression failures and to the decompressed data otherwise.
+    #[error("corrupt cluster {cluster_idx} at byte {byte_offset}: {detail}")]
+    CorruptCluster {
+        cluster_idx: u32,
+        byte_offset: u64,
+        detail: String,
+    },
+    /// An entry with the same namespace and URL has already been added to the writer.
+    #[error("duplicate url {url:?} in namespace {namespace:?}")]
+    DuplicateUrl { namespace: Namespace, url: String },
+    /// A redirect points to an entry which has not been added to the writer.
+    #[error("unresolved redirect from {from_url:?} to {to_url:?}")]
+    UnresolvedRedirect { from_url: String, to_url: String },
+    #[error("namespace has no entries")]
+    EmptyNamespace,
+    #[error("too many redirects starting at {url:?}")]
+    RedirectLoop { url: String },
+    #[error("entry is a redirect")]
+    IsRedirect,
+    #[error("entry has no target")]
+    NoTarget,
+    #[error("string contains a null byte: {0:?}")]
+    NullByte(String),
+    #[error("no search index has been opened")]
+    MissingSearchIndex,
+    #[error("search index failure: {0}")]
+    SearchIndex(#[source] Box<dyn std::error::Error + Send + Sync>),
+    #[error("i/o error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid utf-8: {0}")]
+    Utf8(#[from] std::string::FromUtf8Error),
+    #[error("failed to read bits: {0}")]
+    BitReader(#[from] bitreader::BitReaderError),
 }
@@ -0,0 +1,209 @@
+//! C API, enabled by the `ffi` feature.
+//!
+//! The matching header is `zim.h` in the root of the repository. It is generated with
+//! `cbindgen --config cbindgen.toml --output zim.h src/lib.rs`.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::ffi::CStr;
+use std::os::raw::{c_char, c_int};
+use std::panic;
+use std::ptr;
+use std::sync::Mutex;
+
+use crate::zim::Zim;
+
+/// The call succeeded.
+pub const ZIM_OK: c_int = 0;
+/// A pointer argument is null, an index is out of range or the file could not be read.
+pub const ZIM_ERROR: c_int = -1;
+/// The output buffer is too small for the result, including the terminating null byte.
+pub const ZIM_BUFFER_TOO_SMALL: c_int = -2;
+
+/// Blobs by cluster and blob index.
+type Blobs = HashMap<(u32, u32), Box<[u8]>>;
+
+/// An open ZIM file.
+pub struct ZimHandle {
+    zim: Zim,
+    /// Blobs handed out by `zim_get_blob`, which stay valid until the handle is closed.
+    blobs: Mutex<Blobs>,
+}
+
+/// Opens the ZIM file at `path`, returning null on failure.
+///
+/// # Safety
+///
+/// `path` must be null or point to a null terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn zim_open(path: *const c_char) -> *mut ZimHandle {
+    if path.is_null() {
+        return ptr::null_mut();
+    }
+    let path = match CStr::from_ptr(path).to_str() {
+        Ok(path) => path,
+        Err(_) => return ptr::null_mut(),
+    };
+
+    match panic::catch_unwind(|| Zim::new(path)) {
+        Ok(Ok(zim)) => Box::into_raw(Box::new(ZimHandle {
+            zim,
+            blobs: Mutex::new(HashMap::new()),
+        })),
+        _ => ptr::null_mut(),
+    }
+}
+
+/// Closes a handle returned by `zim_open`. All blobs returned for it become invalid.
+///
+/// # Safety
+///
+/// `h` must be null or a handle returned by `zim_open` which has not been closed yet.
+#[no_mangle]
+pub unsafe extern "C" fn zim_close(h: *mut ZimHandle) {
+    if !h.is_null() {
+        drop(Box::from_raw(h));
+    }
+}
+
+/// Returns the number of entries, or 0 if `h` is null.
+///
+/// # Safety
+///
+/// `h` must be null or a valid handle.
+#[no_mangle]
+pub unsafe extern "C" fn zim_get_article_count(h: *const ZimHandle) -> u32 {
+    match h.as_ref() {
+        Some(handle) => handle.zim.article_count() as u32,
+        None => 0,
+    }
+}
+
+/// Copies the URL of the entry at URL index `idx` into `out`, followed by a null byte.
+///
+/// Returns `ZIM_BUFFER_TOO_SMALL` if the URL and the null byte do not fit into `len` bytes.
+///
+/// # Safety
+///
+/// `h` must be null or a valid handle, and `out` must be null or point to `len` writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn zim_get_entry_url(
+    h: *const ZimHandle,
+    idx: u32,
+    out: *mut c_char,
+    len: usize,
+) -> c_int {
+    let handle = match h.as_ref() {
+        Some(handle) => handle,
+        None => return ZIM_ERROR,
+    };
+    if out.is_null() || idx as usize >= handle.zim.url_list.len() {
+        return ZIM_ERROR;
+    }
+
+    let url = match panic::catch_unwind(|| handle.zim.get_by_url_index(idx)) {
+        Ok(Ok(entry)) => entry.url,
+        _ => return ZIM_ERROR,
+    };
+    if url.len() >= len {
+        return ZIM_BUFFER_TOO_SMALL;
+    }
+
+    ptr::copy_nonoverlapping(url.as_ptr() as *const c_char, out, url.len());
+    *out.add(url.len()) = 0;
+    ZIM_OK
+}
+
+/// Sets `out` and `len` to the content of a blob.
+///
+/// The blob is owned by the handle and stays valid until `zim_close`.
+///
+/// # Safety
+///
+/// `h` must be null or a valid handle, and `out` and `len` must be null or writable.
+#[no_mangle]
+pub unsafe extern "C" fn zim_get_blob(
+    h: *const ZimHandle,
+    cluster_idx: u32,
+    blob_idx: u32,
+    out: *mut *const u8,
+    len: *mut usize,
+) -> c_int {
+    let handle = match h.as_ref() {
+        Some(handle) => handle,
+        None => return ZIM_ERROR,
+    };
+    if out.is_null() || len.is_null() || cluster_idx as usize >= handle.zim.cluster_list.len() {
+        return ZIM_ERROR;
+    }
+
+    let mut blobs = match handle.blobs.lock() {
+        Ok(blobs) => blobs,
+        Err(poisoned) => poisoned.into_inner(),
+    };
+    let blob = match blobs.entry((cluster_idx, blob_idx)) {
+        Entry::Occupied(entry) => entry.into_mut(),
+        Entry::Vacant(entry) => {
+            let read = panic::catch_unwind(|| {
+                let cluster = handle.zim.get_cluster(cluster_idx)?;
+                cluster.map_blob(blob_idx, |blob| blob.to_vec())
+            });
+            match read {
+                Ok(Ok(data)) => entry.insert(data.into_boxed_slice()),
+                _ => return ZIM_ERROR,
+            }
+        }
+    };
+    *out = blob.as_ptr();
+    *len = blob.len();
+    ZIM_OK
+}
+
+#[test]
+fn test_ffi() {
+    use std::ffi::CString;
+    use std::slice;
+
+    use crate::namespace::Namespace;
+    use crate::target::Target;
+
+    let path = crate::zim::temp_path("ffi");
+    let raw = crate::zim::write_articles(&[(Namespace::Articles, "Apple", "")]);
+    std::fs::write(&path, &raw).unwrap();
+
+    unsafe {
+        let c_path = CString::new(path.to_str().unwrap()).unwrap();
+        let h = zim_open(c_path.as_ptr());
+        std::fs::remove_file(&path).unwrap();
+        assert!(!h.is_null());
+        assert_eq!(zim_get_article_count(h), 1);
+
+        let mut url = [0 as c_char; 6];
+        assert_eq!(
+            zim_get_entry_url(h, 0, url.as_mut_ptr(), 5),
+            ZIM_BUFFER_TOO_SMALL
+        );
+        assert_eq!(zim_get_entry_url(h, 0, url.as_mut_ptr(), 6), ZIM_OK);
+        assert_eq!(CStr::from_ptr(url.as_ptr()).to_str().unwrap(), "Apple");
+        assert_eq!(zim_get_entry_url(h, 1, url.as_mut_ptr(), 6), ZIM_ERROR);
+
+        let (cluster_idx, blob_idx) = match (*h).zim.get_by_url_index(0).unwrap().target {
+            Some(Target::Cluster(cluster_idx, blob_idx)) => (cluster_idx, blob_idx),
+            _ => panic!("expected an article"),
+        };
+        let mut data = ptr::null();
+        let mut len = 0;
+        assert_eq!(
+            zim_get_blob(h, cluster_idx, blob_idx, &mut data, &mut len),
+            ZIM_OK
+        );
+        assert_eq!(slice::from_raw_parts(data, len), b"Apple");
+        assert_eq!(
+            zim_get_blob(h, cluster_idx, blob_idx + 1, &mut data, &mut len),
+            ZIM_ERROR
+        );
+
+        zim_close(h);
+        assert!(zim_open(ptr::null()).is_null());
+    }
+}
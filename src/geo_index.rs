@@ -0,0 +1,21 @@
+/// The geo index of a ZIM file, found at `ZimHeader::geo_index_pos`.
+///
+/// The OpenZIM spec reserves the header field for an index of article coordinates, but does not
+/// define its layout, and the common tools don't write one. Until there is a layout to parse,
+/// only the raw bytes are exposed: they start at `geo_index_pos` and end where the next known
+/// part of the file starts.
+#[derive(Debug, Clone, Copy)]
+pub struct GeoIndex<'a> {
+    raw: &'a [u8],
+}
+
+impl<'a> GeoIndex<'a> {
+    pub(crate) fn new(raw: &'a [u8]) -> GeoIndex<'a> {
+        GeoIndex { raw }
+    }
+
+    /// The bytes of the index.
+    pub fn raw(&self) -> &'a [u8] {
+        self.raw
+    }
+}
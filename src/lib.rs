@@ -9,20 +9,47 @@
 #[macro_use]
 extern crate rental;
 
+mod checksum;
 mod cluster;
 mod directory_entry;
 mod directory_iterator;
 mod errors;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod geo_index;
+mod merge;
 mod mime_type;
 mod namespace;
+#[cfg(feature = "tantivy")]
+mod search;
 mod target;
 mod uuid;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+mod writer;
 mod zim;
 
-pub use crate::cluster::Cluster;
+#[cfg(all(target_arch = "wasm32", not(feature = "wasm")))]
+compile_error!("building for wasm32 requires the `wasm` feature");
+
+pub use crate::checksum::{ChecksumType, ChecksumValue};
+pub use crate::cluster::{
+    Blob, BlobIter, Cluster, ClusterBacking, ClusterBuilder, Compression, DecompressPool,
+};
 pub use crate::directory_entry::DirectoryEntry;
+pub use crate::directory_iterator::{DirectoryIterator, ZimParIter};
+pub use crate::errors::{Error, Result};
+pub use crate::geo_index::GeoIndex;
+pub use crate::merge::MergeReport;
 pub use crate::mime_type::MimeType;
 pub use crate::namespace::Namespace;
+#[cfg(feature = "tantivy")]
+pub use crate::search::{SearchResult, SEARCH_RESULT_LIMIT};
 pub use crate::target::Target;
-pub use crate::uuid::Uuid;
-pub use crate::zim::Zim;
+pub use crate::uuid::{Uuid, UuidError};
+pub use crate::writer::{ZimWriter, ZimWriterBuilder, DEFAULT_CLUSTER_SIZE};
+pub use crate::zim::{
+    MasterView, PointerViolation, ValidationWarning, WarningKind, Zim, ZimOpenOptions,
+};
+#[cfg(unix)]
+pub use memmap2::Advice;
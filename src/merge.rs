@@ -0,0 +1,256 @@
+//! Combining several ZIM files into one.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use crate::directory_entry::DirectoryEntry;
+use crate::errors::{Error, Result};
+use crate::mime_type::MimeType;
+use crate::target::Target;
+use crate::writer::ZimWriterBuilder;
+use crate::zim::Zim;
+
+/// What `Zim::merge` left out of the merged file.
+#[derive(Debug, Default)]
+pub struct MergeReport {
+    /// `(from_url, to_url)` of each redirect dropped because its target is missing.
+    pub dangling_redirects: Vec<(String, String)>,
+    /// `(input_idx, url_idx, error)` of each entry skipped because it, its redirect target or its
+    /// content could not be read.
+    pub broken_entries: Vec<(usize, u32, Error)>,
+}
+
+impl Zim {
+    /// Merges `inputs` into a single ZIM file, written to `writer`.
+    ///
+    /// When several inputs contain the same namespace and URL, the entry of the first one is
+    /// kept. The content is reclustered and the MIME types are re-indexed. The main page of the
+    /// first input is kept, if it has one.
+    ///
+    /// Entries which can't be read are skipped, and redirects whose target does not end up in the
+    /// merged file are dropped; both are listed in the returned `MergeReport`. Only a cluster
+    /// which can't be located, or a failure to write, fails the whole merge.
+    pub fn merge<W: Write>(inputs: &[Zim], writer: W) -> Result<MergeReport> {
+        Zim::merge_with_progress(inputs, writer, |_, _| {})
+    }
+
+    /// Like `merge`, calling `progress` with the number of entries processed so far and the
+    /// total number of entries of all inputs.
+    pub fn merge_with_progress<W, F>(
+        inputs: &[Zim],
+        writer: W,
+        mut progress: F,
+    ) -> Result<MergeReport>
+    where
+        W: Write,
+        F: FnMut(u64, u64),
+    {
+        let total = inputs
+            .iter()
+            .map(|zim| zim.header.article_count as u64)
+            .sum();
+        let mut current = 0;
+        let mut report = MergeReport::default();
+        let mut out = ZimWriterBuilder::new()
+            .allow_dangling_redirects(true)
+            .build();
+
+        for (input_idx, zim) in inputs.iter().enumerate() {
+            // group the content by cluster, so each cluster is only decompressed once
+            let mut clusters: BTreeMap<u32, Vec<(u32, DirectoryEntry)>> = BTreeMap::new();
+            for (url_idx, entry) in (0..).zip(zim.try_iterate_by_urls()) {
+                match entry {
+                    Ok(entry) => match entry.target {
+                        Some(Target::Cluster(cluster_idx, _)) => {
+                            clusters
+                                .entry(cluster_idx)
+                                .or_default()
+                                .push((url_idx, entry));
+                            // counted once its content has been copied
+                            continue;
+                        }
+                        Some(Target::Redirect(target_idx)) => {
+                            match zim.get_by_url_index(target_idx) {
+                                Ok(target) => skip_duplicate(out.add_redirect(
+                                    entry.namespace,
+                                    &entry.url,
+                                    &entry.title,
+                                    &target.url,
+                                    target.namespace,
+                                ))?,
+                                Err(err) => report.broken_entries.push((input_idx, url_idx, err)),
+                            }
+                        }
+                        None => {}
+                    },
+                    Err(err) => report.broken_entries.push((input_idx, url_idx, err)),
+                }
+
+                current += 1;
+                progress(current, total);
+            }
+
+            for (cluster_idx, entries) in clusters {
+                let cluster = zim.get_cluster(cluster_idx)?;
+                for (url_idx, entry) in entries {
+                    if let (Some(Target::Cluster(_, blob_idx)), MimeType::Type(mime)) =
+                        (&entry.target, &entry.mime_type)
+                    {
+                        match cluster.get_blob(*blob_idx) {
+                            Ok(blob) => skip_duplicate(out.add_article(
+                                entry.namespace,
+                                &entry.url,
+                                &entry.title,
+                                mime,
+                                &blob,
+                            ))?,
+                            Err(err) => report.broken_entries.push((input_idx, url_idx, err)),
+                        }
+                    }
+
+                    current += 1;
+                    progress(current, total);
+                }
+            }
+
+            if input_idx == 0 {
+                if let Some(main_page) = zim.header.main_page {
+                    match zim.get_by_url_index(main_page) {
+                        Ok(entry) => out.set_main_page(entry.namespace, &entry.url),
+                        Err(err) => report.broken_entries.push((input_idx, main_page, err)),
+                    }
+                }
+            }
+        }
+
+        report.dangling_redirects = out.dangling_redirects();
+        out.finalize(writer)?;
+        Ok(report)
+    }
+}
+
+/// An entry which is already present comes from an earlier input, which takes precedence.
+fn skip_duplicate(result: Result<()>) -> Result<()> {
+    match result {
+        Err(Error::DuplicateUrl { .. }) => Ok(()),
+        result => result,
+    }
+}
+
+#[test]
+fn test_merge() {
+    use std::io::Cursor;
+
+    use crate::namespace::Namespace;
+
+    fn build(articles: &[(&str, &[u8])], redirects: &[(&str, &str)], main_page: &str) -> Zim {
+        let mut writer = crate::writer::ZimWriter::new();
+        for &(url, data) in articles {
+            writer
+                .add_article(Namespace::Articles, url, "", "text/html", data)
+                .unwrap();
+        }
+        for &(url, target) in redirects {
+            writer
+                .add_redirect(Namespace::Articles, url, "", target, Namespace::Articles)
+                .unwrap();
+        }
+        writer.set_main_page(Namespace::Articles, main_page);
+        crate::zim::open_written(writer)
+    }
+
+    let first = build(&[("Apple", b"apple"), ("Main", b"main")], &[], "Main");
+    let second = build(
+        &[("Apple", b"other apple"), ("Banana", b"banana")],
+        &[("Fruit", "Banana")],
+        "Banana",
+    );
+
+    let mut steps = Vec::new();
+    let mut raw = Vec::new();
+    let report = Zim::merge_with_progress(&[first, second], &mut raw, |current, total| {
+        steps.push((current, total))
+    })
+    .unwrap();
+    assert!(report.dangling_redirects.is_empty());
+    assert!(report.broken_entries.is_empty());
+    assert_eq!(steps.len(), 5);
+    assert_eq!(steps.last(), Some(&(5, 5)));
+
+    let merged = Zim::new_from_reader(Cursor::new(raw)).unwrap();
+    merged.verify_checksum().unwrap();
+
+    let urls: Vec<String> = merged.iterate_by_urls().map(|entry| entry.url).collect();
+    assert_eq!(urls, vec!["Apple", "Banana", "Fruit", "Main"]);
+
+    let (_, apple) = merged
+        .article_at_url(Namespace::Articles, "Apple")
+        .unwrap()
+        .unwrap();
+    assert_eq!(apple, b"apple");
+    let (banana, _) = merged
+        .article_at_url(Namespace::Articles, "Fruit")
+        .unwrap()
+        .unwrap();
+    assert_eq!(banana.url, "Banana");
+
+    let main_page = merged.header.main_page.unwrap();
+    assert_eq!(merged.get_by_url_index(main_page).unwrap().url, "Main");
+}
+
+#[test]
+fn test_merge_broken_input() {
+    use std::io::Cursor;
+
+    use crate::cluster::Compression;
+    use crate::namespace::Namespace;
+
+    let mut writer = ZimWriterBuilder::new()
+        .compression(Compression::None)
+        .build();
+    for &(url, data) in &[
+        ("Apple", b"apple"),
+        ("Banana", b"banan"),
+        ("Cherry", b"cherr"),
+    ] {
+        writer
+            .add_article(Namespace::Articles, url, "", "text/html", data)
+            .unwrap();
+    }
+    writer
+        .add_redirect(
+            Namespace::Articles,
+            "Fruit",
+            "",
+            "Banana",
+            Namespace::Articles,
+        )
+        .unwrap();
+    let mut raw = Vec::new();
+    writer.finalize(&mut raw).unwrap();
+
+    // the URL of "Cherry" is not valid UTF-8, and the blob of "Banana" ends past its cluster
+    let pos = raw.windows(7).position(|w| w == b"Cherry\0").unwrap();
+    raw[pos] = 0xff;
+    let start = Zim::new_from_reader(Cursor::new(raw.clone()))
+        .unwrap()
+        .cluster_list[0] as usize;
+    raw[start + 9..start + 13].copy_from_slice(&u32::MAX.to_le_bytes());
+    let input = Zim::new_from_reader(Cursor::new(raw)).unwrap();
+
+    let mut raw = Vec::new();
+    let report = Zim::merge(&[input], &mut raw).unwrap();
+    assert_eq!(
+        report.dangling_redirects,
+        [("Fruit".to_string(), "Banana".to_string())]
+    );
+    // URL order: Apple, Banana, Cherry, Fruit
+    assert!(matches!(
+        report.broken_entries[..],
+        [(0, 2, Error::Utf8(_)), (0, 1, Error::CorruptCluster { .. })]
+    ));
+
+    let merged = Zim::new_from_reader(Cursor::new(raw)).unwrap();
+    let urls: Vec<String> = merged.iterate_by_urls().map(|entry| entry.url).collect();
+    assert_eq!(urls, ["Apple"]);
+}
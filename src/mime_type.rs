@@ -1,3 +1,7 @@
+use std::convert::Infallible;
+use std::fmt;
+use std::str::FromStr;
+
 /// Representation of MimeTypes.
 #[derive(Debug, PartialEq)]
 pub enum MimeType {
@@ -7,3 +11,105 @@ pub enum MimeType {
     DeletedEntry,
     Type(String),
 }
+
+/// File extensions of common MIME types. The first extension of a MIME type is the one used
+/// when extracting.
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("text/html", "html"),
+    ("image/jpeg", "jpg"),
+    ("image/png", "png"),
+    ("image/gif", "gif"),
+    ("image/svg+xml", "svg"),
+    ("application/javascript", "js"),
+    ("text/css", "css"),
+    ("text/plain", "txt"),
+    ("application/xhtml+xml", "xhtml"),
+    ("image/webp", "webp"),
+    ("application/pdf", "pdf"),
+    ("video/mp4", "mp4"),
+    ("audio/mpeg", "mp3"),
+    ("application/json", "json"),
+    ("application/xml", "xml"),
+    ("font/woff", "woff"),
+    ("font/woff2", "woff2"),
+    ("text/html", "htm"),
+    ("image/jpeg", "jpeg"),
+];
+
+impl MimeType {
+    /// Guesses the MIME type from a file extension, ignoring case.
+    pub fn from_extension(extension: &str) -> Option<MimeType> {
+        let extension = extension.to_lowercase();
+        EXTENSIONS
+            .iter()
+            .find(|&&(_, ext)| ext == extension)
+            .map(|&(mime, _)| MimeType::Type(mime.into()))
+    }
+
+    /// The usual file extension for this MIME type, if it is a common one.
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            MimeType::Type(mime) => EXTENSIONS
+                .iter()
+                .find(|&&(typ, _)| typ == mime)
+                .map(|&(_, ext)| ext),
+            _ => None,
+        }
+    }
+}
+
+/// Prints the MIME type, or `<redirect>`, `<link-target>` or `<deleted>` for the special entries.
+impl fmt::Display for MimeType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MimeType::Redirect => write!(f, "<redirect>"),
+            MimeType::LinkTarget => write!(f, "<link-target>"),
+            MimeType::DeletedEntry => write!(f, "<deleted>"),
+            MimeType::Type(mime) => write!(f, "{}", mime),
+        }
+    }
+}
+
+/// Wraps any string as `MimeType::Type`.
+///
+/// The special values printed by `Display` are not parsed back, as they are not MIME types.
+impl FromStr for MimeType {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> Result<MimeType, Infallible> {
+        Ok(MimeType::Type(s.to_owned()))
+    }
+}
+
+#[test]
+fn test_extensions() {
+    let html = MimeType::Type("text/html".into());
+    assert_eq!(MimeType::from_extension("HTM"), Some(html));
+    assert_eq!(MimeType::Type("text/html".into()).extension(), Some("html"));
+    assert_eq!(MimeType::from_extension("unknown"), None);
+    assert_eq!(
+        MimeType::Type("font/woff2".into()).extension(),
+        Some("woff2")
+    );
+    assert_eq!(
+        MimeType::from_extension("json").unwrap().extension(),
+        Some("json")
+    );
+    assert_eq!(MimeType::Redirect.extension(), None);
+    assert_eq!(MimeType::LinkTarget.extension(), None);
+    assert_eq!(MimeType::DeletedEntry.extension(), None);
+}
+
+#[test]
+fn test_display() {
+    let html: MimeType = "text/html".parse().unwrap();
+    assert_eq!(html, MimeType::Type("text/html".into()));
+    assert_eq!(html.to_string(), "text/html");
+    assert_eq!(MimeType::Redirect.to_string(), "<redirect>");
+    assert_eq!(MimeType::LinkTarget.to_string(), "<link-target>");
+    assert_eq!(MimeType::DeletedEntry.to_string(), "<deleted>");
+    assert_eq!(
+        "<redirect>".parse::<MimeType>().unwrap(),
+        MimeType::Type("<redirect>".into())
+    );
+}
@@ -1,15 +1,22 @@
+use std::cmp::Ordering;
 use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
 
 use crate::errors::{Error, Result};
 
 /// Namespaces seperate different types of directory entries - which might have the same title -
 /// stored in the ZIM File Format.
-#[derive(Debug, Copy, Clone)]
+///
+/// Files written by openzim-creator 2.0 and later put all content into the single `Content`
+/// namespace instead of the `A`, `I` and `J` namespaces.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
 #[repr(u8)]
 pub enum Namespace {
     Layout = b'-',
     Articles = b'A',
     ArticleMetaData = b'B',
+    Content = b'C',
     ImagesFile = b'I',
     ImagesText = b'J',
     Metadata = b'M',
@@ -28,6 +35,7 @@ impl TryFrom<u8> for Namespace {
             b'-' => Ok(Layout),
             b'A' => Ok(Articles),
             b'B' => Ok(ArticleMetaData),
+            b'C' => Ok(Content),
             b'I' => Ok(ImagesFile),
             b'J' => Ok(ImagesText),
             b'M' => Ok(Metadata),
@@ -35,7 +43,150 @@ impl TryFrom<u8> for Namespace {
             b'V' => Ok(CategoriesArticleList),
             b'W' => Ok(CategoriesArticle),
             b'X' => Ok(FulltextIndex),
-            _ => Err(Error::InvalidNamespace),
+            _ => Err(Error::InvalidNamespace(value)),
         }
     }
 }
+
+impl Namespace {
+    /// All namespaces, in byte order.
+    pub const ALL: [Namespace; 11] = [
+        Namespace::Layout,
+        Namespace::Articles,
+        Namespace::ArticleMetaData,
+        Namespace::Content,
+        Namespace::ImagesFile,
+        Namespace::ImagesText,
+        Namespace::Metadata,
+        Namespace::CategoriesText,
+        Namespace::CategoriesArticleList,
+        Namespace::CategoriesArticle,
+        Namespace::FulltextIndex,
+    ];
+
+    /// Parses the namespace character used in full URLs, like the `A` in `A/Main_Page`.
+    pub fn from_char(c: char) -> Result<Namespace> {
+        if !c.is_ascii() {
+            return Err(Error::InvalidNamespaceStr(c.to_string()));
+        }
+        Namespace::try_from(c as u8)
+    }
+
+    pub fn as_char(self) -> char {
+        self as u8 as char
+    }
+
+    /// Whether entries in this namespace are content shown to readers: articles and images.
+    pub fn is_content(self) -> bool {
+        matches!(
+            self,
+            Namespace::Articles
+                | Namespace::Content
+                | Namespace::ImagesFile
+                | Namespace::ImagesText
+        )
+    }
+
+    /// Whether this is the namespace of metadata like the title or language of the file.
+    pub fn is_metadata(self) -> bool {
+        self == Namespace::Metadata
+    }
+
+    /// Whether this is the namespace of the full-text search index.
+    pub fn is_index(self) -> bool {
+        self == Namespace::FulltextIndex
+    }
+
+    /// Whether entries in this namespace describe categories.
+    pub fn is_category(self) -> bool {
+        matches!(
+            self,
+            Namespace::CategoriesText
+                | Namespace::CategoriesArticleList
+                | Namespace::CategoriesArticle
+        )
+    }
+}
+
+/// Parses a single namespace character, as accepted by `Namespace::from_char`.
+impl FromStr for Namespace {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Namespace> {
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Namespace::from_char(c),
+            _ => Err(Error::InvalidNamespaceStr(s.to_owned())),
+        }
+    }
+}
+
+impl fmt::Display for Namespace {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_char())
+    }
+}
+
+/// Namespaces are ordered by their byte value, as in the URL pointer list.
+impl Ord for Namespace {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (*self as u8).cmp(&(*other as u8))
+    }
+}
+
+impl PartialOrd for Namespace {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+#[test]
+fn test_char_conversion() {
+    assert_eq!(Namespace::from_char('A').unwrap(), Namespace::Articles);
+    assert_eq!(Namespace::from_char('-').unwrap(), Namespace::Layout);
+    assert_eq!(Namespace::from_char('C').unwrap(), Namespace::Content);
+    assert!(matches!(
+        Namespace::from_char('a'),
+        Err(Error::InvalidNamespace(b'a'))
+    ));
+    match Namespace::from_char('\u{141}') {
+        Err(Error::InvalidNamespaceStr(s)) => assert_eq!(s, "\u{141}"),
+        other => panic!("expected InvalidNamespaceStr, got {:?}", other),
+    }
+
+    assert_eq!(Namespace::ImagesFile.as_char(), 'I');
+    assert_eq!(Namespace::Metadata.to_string(), "M");
+    assert!(Namespace::Layout < Namespace::Articles);
+    assert!(Namespace::FulltextIndex > Namespace::CategoriesArticle);
+    assert_eq!("X".parse::<Namespace>().unwrap(), Namespace::FulltextIndex);
+    for s in &["AX", ""] {
+        match s.parse::<Namespace>() {
+            Err(Error::InvalidNamespaceStr(ref invalid)) => assert_eq!(invalid, s),
+            other => panic!("expected InvalidNamespaceStr, got {:?}", other),
+        }
+    }
+    assert!(matches!(
+        "?".parse::<Namespace>(),
+        Err(Error::InvalidNamespace(b'?'))
+    ));
+    assert!(Namespace::ALL.windows(2).all(|pair| pair[0] < pair[1]));
+}
+
+#[test]
+fn test_groups() {
+    let content: Vec<_> = Namespace::ALL.iter().filter(|ns| ns.is_content()).collect();
+    assert_eq!(
+        content,
+        [
+            &Namespace::Articles,
+            &Namespace::Content,
+            &Namespace::ImagesFile,
+            &Namespace::ImagesText
+        ]
+    );
+    assert!(Namespace::Metadata.is_metadata());
+    assert!(!Namespace::Articles.is_metadata());
+    assert!(Namespace::FulltextIndex.is_index());
+    assert!(Namespace::CategoriesArticle.is_category());
+    assert!(!Namespace::Layout.is_content() && !Namespace::Layout.is_category());
+}
@@ -0,0 +1,149 @@
+//! Full-text search over the HTML articles of a ZIM file, backed by tantivy.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use tantivy::collector::TopDocs;
+use tantivy::query::QueryParser;
+use tantivy::schema::{Schema, Value, STORED, STRING, TEXT};
+use tantivy::{doc, Index, IndexWriter, TantivyDocument};
+
+use crate::errors::{Error, Result};
+use crate::mime_type::MimeType;
+use crate::target::Target;
+use crate::zim::Zim;
+
+/// Maximum number of results returned by `Zim::search`.
+pub const SEARCH_RESULT_LIMIT: usize = 20;
+
+/// Memory used by the index writer while building the index.
+const WRITER_MEMORY: usize = 50_000_000;
+
+/// A single hit of `Zim::search`.
+#[derive(Debug, Clone)]
+pub struct SearchResult {
+    pub url: String,
+    pub title: String,
+    pub score: f32,
+}
+
+impl Zim {
+    /// Writes a full-text search index over all `text/html` articles to `output`.
+    ///
+    /// The index stores the url and title of each article and indexes the title and the text of
+    /// the article. Afterwards the index is used by `search`.
+    pub fn export_search_index(&self, output: &Path) -> Result<()> {
+        std::fs::create_dir_all(output)?;
+
+        let schema = search_schema();
+        let url = schema.get_field("url").unwrap();
+        let title = schema.get_field("title").unwrap();
+        let body = schema.get_field("body").unwrap();
+
+        let index = Index::create_in_dir(output, schema).map_err(search_err)?;
+        let mut writer: IndexWriter = index.writer(WRITER_MEMORY).map_err(search_err)?;
+
+        // group by cluster, so every cluster is only decompressed once
+        let mut clusters: HashMap<u32, Vec<_>> = HashMap::new();
+        for entry in self.iterate_by_urls() {
+            let is_html = match entry.mime_type {
+                MimeType::Type(ref mime) => mime == "text/html",
+                _ => false,
+            };
+            if let (true, Some(Target::Cluster(cluster_idx, blob_idx))) = (is_html, &entry.target) {
+                let (cluster_idx, blob_idx) = (*cluster_idx, *blob_idx);
+                clusters
+                    .entry(cluster_idx)
+                    .or_default()
+                    .push((blob_idx, entry));
+            }
+        }
+
+        for (cluster_idx, entries) in clusters {
+            let cluster = self.get_cluster(cluster_idx)?;
+            for (blob_idx, entry) in entries {
+                let blob = cluster.get_blob(blob_idx)?;
+                let text = html2text::from_read(&blob[..], usize::MAX);
+                let entry_title = if entry.title.is_empty() {
+                    &entry.url
+                } else {
+                    &entry.title
+                };
+
+                writer
+                    .add_document(doc!(
+                        url => entry.url.as_str(),
+                        title => entry_title.as_str(),
+                        body => text,
+                    ))
+                    .map_err(search_err)?;
+            }
+        }
+
+        writer.commit().map_err(search_err)?;
+        *self.search_index.write().unwrap() = Some(index);
+
+        Ok(())
+    }
+
+    /// Uses the search index previously written to `path` by `export_search_index`.
+    pub fn open_search_index(&self, path: &Path) -> Result<()> {
+        let index = Index::open_in_dir(path).map_err(search_err)?;
+        *self.search_index.write().unwrap() = Some(index);
+
+        Ok(())
+    }
+
+    /// Searches the articles for `query`, returning the best `SEARCH_RESULT_LIMIT` matches.
+    ///
+    /// Requires a search index, see `export_search_index` and `open_search_index`.
+    pub fn search(&self, query: &str) -> Result<Vec<SearchResult>> {
+        let lock = self.search_index.read().unwrap();
+        let index = lock.as_ref().ok_or(Error::MissingSearchIndex)?;
+
+        let schema = index.schema();
+        let url = schema.get_field("url").map_err(search_err)?;
+        let title = schema.get_field("title").map_err(search_err)?;
+        let body = schema.get_field("body").map_err(search_err)?;
+
+        let searcher = index.reader().map_err(search_err)?.searcher();
+        let query = QueryParser::for_index(index, vec![title, body])
+            .parse_query(query)
+            .map_err(search_err)?;
+
+        let top_docs = searcher
+            .search(&query, &TopDocs::with_limit(SEARCH_RESULT_LIMIT))
+            .map_err(search_err)?;
+
+        let mut results = Vec::with_capacity(top_docs.len());
+        for (score, address) in top_docs {
+            let doc: TantivyDocument = searcher.doc(address).map_err(search_err)?;
+            let text = |field| {
+                doc.get_first(field)
+                    .and_then(|value| value.as_str())
+                    .unwrap_or_default()
+                    .to_string()
+            };
+
+            results.push(SearchResult {
+                url: text(url),
+                title: text(title),
+                score,
+            });
+        }
+
+        Ok(results)
+    }
+}
+
+fn search_schema() -> Schema {
+    let mut builder = Schema::builder();
+    builder.add_text_field("url", STRING | STORED);
+    builder.add_text_field("title", TEXT | STORED);
+    builder.add_text_field("body", TEXT);
+    builder.build()
+}
+
+fn search_err<E: std::error::Error + Send + Sync + 'static>(err: E) -> Error {
+    Error::SearchIndex(err.into())
+}
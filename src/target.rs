@@ -5,3 +5,52 @@ pub enum Target {
     /// Cluster index and blob index
     Cluster(u32, u32),
 }
+
+impl Target {
+    /// The cluster index, if this points into a cluster.
+    pub fn cluster_index(&self) -> Option<u32> {
+        match *self {
+            Target::Cluster(cluster, _) => Some(cluster),
+            Target::Redirect(_) => None,
+        }
+    }
+
+    /// The blob index within the cluster, if this points into a cluster.
+    pub fn blob_index(&self) -> Option<u32> {
+        match *self {
+            Target::Cluster(_, blob) => Some(blob),
+            Target::Redirect(_) => None,
+        }
+    }
+
+    /// The URL index of the redirect target, if this is a redirect.
+    pub fn redirect_index(&self) -> Option<u32> {
+        match *self {
+            Target::Redirect(idx) => Some(idx),
+            Target::Cluster(..) => None,
+        }
+    }
+
+    pub fn is_redirect(&self) -> bool {
+        matches!(self, Target::Redirect(_))
+    }
+
+    pub fn is_cluster(&self) -> bool {
+        matches!(self, Target::Cluster(..))
+    }
+}
+
+#[test]
+fn test_accessors() {
+    let cluster = Target::Cluster(3, 7);
+    assert_eq!(cluster.cluster_index(), Some(3));
+    assert_eq!(cluster.blob_index(), Some(7));
+    assert_eq!(cluster.redirect_index(), None);
+    assert!(cluster.is_cluster() && !cluster.is_redirect());
+
+    let redirect = Target::Redirect(5);
+    assert_eq!(redirect.cluster_index(), None);
+    assert_eq!(redirect.blob_index(), None);
+    assert_eq!(redirect.redirect_index(), Some(5));
+    assert!(redirect.is_redirect() && !redirect.is_cluster());
+}
@@ -1,8 +1,17 @@
+use std::convert::TryFrom;
 use std::fmt;
 
+use thiserror::Error;
+
 const HEX: &[u8] = b"0123456789abcdef";
 
-#[derive(Debug)]
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum UuidError {
+    #[error("invalid uuid length {0}, must be 16 bytes")]
+    InvalidLength(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Uuid([u8; 16]);
 
 impl Uuid {
@@ -10,6 +19,14 @@ impl Uuid {
         Uuid(uuid)
     }
 
+    pub fn as_bytes(&self) -> &[u8; 16] {
+        &self.0
+    }
+
+    pub fn to_bytes(&self) -> [u8; 16] {
+        self.0
+    }
+
     fn hi(&self, i: usize) -> u8 {
         HEX[((self.0[i] >> 4) & 0xF) as usize]
     }
@@ -19,6 +36,20 @@ impl Uuid {
     }
 }
 
+impl TryFrom<&[u8]> for Uuid {
+    type Error = UuidError;
+
+    fn try_from(bytes: &[u8]) -> Result<Self, UuidError> {
+        let mut uuid = [0u8; 16];
+        if bytes.len() != uuid.len() {
+            return Err(UuidError::InvalidLength(bytes.len()));
+        }
+        uuid.copy_from_slice(bytes);
+
+        Ok(Uuid(uuid))
+    }
+}
+
 impl fmt::Display for Uuid {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let print_index = |f: &mut fmt::Formatter<'_>, k: usize| {
@@ -52,3 +83,17 @@ impl fmt::Display for Uuid {
         Ok(())
     }
 }
+
+#[test]
+fn test_try_from() {
+    let bytes: Vec<u8> = (0..16).collect();
+    let uuid = Uuid::try_from(&bytes[..]).unwrap();
+    assert_eq!(&uuid.as_bytes()[..], &bytes[..]);
+    assert_eq!(&uuid.to_bytes()[..], &bytes[..]);
+    assert_eq!(uuid.to_string(), "00010203-0405-0607-0809-0a0b0c0d0e0f");
+
+    assert_eq!(
+        Uuid::try_from(&bytes[..15]).unwrap_err(),
+        UuidError::InvalidLength(15)
+    );
+}
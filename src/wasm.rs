@@ -0,0 +1,78 @@
+//! JavaScript bindings, enabled by the `wasm` feature.
+//!
+//! Build with `wasm-pack build --target web -- --features wasm`. There is no file system in the
+//! browser, so the caller loads the file, e.g. with `fetch`, and passes its bytes to `WasmZim`.
+
+use js_sys::{Array, Uint8Array};
+use wasm_bindgen::prelude::*;
+
+use crate::zim::Zim;
+
+/// An open ZIM file.
+#[wasm_bindgen]
+pub struct WasmZim {
+    zim: Zim,
+}
+
+#[wasm_bindgen]
+impl WasmZim {
+    /// Parses the ZIM file in `data`, throwing if it is invalid.
+    #[wasm_bindgen(constructor)]
+    pub fn new(data: Vec<u8>) -> Result<WasmZim, JsValue> {
+        let zim = Zim::open_from_bytes(data.into()).map_err(to_js_error)?;
+        Ok(WasmZim { zim })
+    }
+
+    /// Returns the number of entries.
+    pub fn get_article_count(&self) -> u32 {
+        self.zim.article_count() as u32
+    }
+
+    /// Returns the URL of the entry at URL index `idx`.
+    pub fn get_entry_url(&self, idx: u32) -> Result<String, JsValue> {
+        let entry = self.zim.get_by_url_index(idx).map_err(to_js_error)?;
+        Ok(entry.url)
+    }
+
+    /// Returns a copy of the content of a blob.
+    pub fn get_blob(&self, cluster_idx: u32, blob_idx: u32) -> Result<Uint8Array, JsValue> {
+        let cluster = self.zim.get_cluster(cluster_idx).map_err(to_js_error)?;
+        cluster
+            .map_blob(blob_idx, |blob| Uint8Array::from(blob))
+            .map_err(to_js_error)
+    }
+
+    /// Returns the full URLs, like `A/Main_Page`, of up to `limit` entries in all namespaces
+    /// whose title starts with `prefix`.
+    pub fn search_title_prefix(
+        &self,
+        prefix: &str,
+        case_insensitive: bool,
+        limit: u32,
+    ) -> Result<Array, JsValue> {
+        let entries = self
+            .zim
+            .title_prefix_matches(prefix, None, case_insensitive)
+            .map_err(to_js_error)?;
+        Ok(entries
+            .iter()
+            .take(limit as usize)
+            .map(|entry| JsValue::from_str(&entry.full_url()))
+            .collect())
+    }
+}
+
+fn to_js_error(err: crate::errors::Error) -> JsValue {
+    JsValue::from_str(&err.to_string())
+}
+
+#[test]
+fn test_wasm_zim() {
+    use crate::namespace::Namespace;
+
+    // only the paths which don't call into JavaScript can run outside of a browser
+    let raw = crate::zim::write_articles(&[(Namespace::Articles, "Apple", "")]);
+    let zim = WasmZim::new(raw).unwrap();
+    assert_eq!(zim.get_article_count(), 1);
+    assert_eq!(zim.get_entry_url(0).unwrap(), "Apple");
+}
@@ -0,0 +1,888 @@
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::mem;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use byteorder::{LittleEndian, WriteBytesExt};
+use md5::{Digest, Md5};
+
+use crate::cluster::{ClusterBuilder, Compression};
+use crate::directory_entry::DirectoryEntry;
+use crate::errors::{Error, Result};
+use crate::mime_type::MimeType;
+use crate::namespace::Namespace;
+use crate::target::Target;
+use crate::zim::{file_region, Zim, ZIM_MAGIC_NUMBER};
+
+/// Size of the header, the MIME table directly follows it.
+const HEADER_SIZE: u64 = 80;
+
+/// MIME type indices with a special meaning.
+const REDIRECT_MIME: u16 = 0xffff;
+const LINK_TARGET_MIME: u16 = 0xfffe;
+const DELETED_ENTRY_MIME: u16 = 0xfffd;
+
+/// Default uncompressed size at which a cluster is closed: 1 MiB.
+pub const DEFAULT_CLUSTER_SIZE: u64 = 1024 * 1024;
+
+/// Builds a new ZIM file.
+///
+/// Articles are packed into clusters in the order they are added. The URL and title pointer
+/// lists are sorted when the file is written, so articles can be added in any order.
+pub struct ZimWriter {
+    cluster_size: u64,
+    compression: Compression,
+    allow_dangling_redirects: bool,
+    mime_table: Vec<String>,
+    entries: Vec<WriterEntry>,
+    /// Index into `entries` by `(namespace, url)`.
+    urls: HashMap<(u8, String), usize>,
+    /// Serialized clusters which are complete.
+    clusters: Vec<Vec<u8>>,
+    current: ClusterBuilder,
+    main_page: Option<(Namespace, String)>,
+}
+
+/// A directory entry which has not been written yet.
+struct WriterEntry {
+    namespace: Namespace,
+    url: String,
+    title: String,
+    target: WriterTarget,
+}
+
+enum WriterTarget {
+    /// MIME type index, cluster index and blob index
+    Article(u16, u32, u32),
+    /// Namespace and URL of the target, resolved to a URL index when the file is written
+    Redirect(Namespace, String),
+}
+
+impl WriterEntry {
+    fn url_key(&self) -> (u8, &str) {
+        (self.namespace as u8, &self.url)
+    }
+
+    /// The URL is used for sorting when the title is empty.
+    fn title_key(&self) -> (u8, &str) {
+        if self.title.is_empty() {
+            self.url_key()
+        } else {
+            (self.namespace as u8, &self.title)
+        }
+    }
+}
+
+/// Configures a `ZimWriter`.
+pub struct ZimWriterBuilder {
+    cluster_size: u64,
+    compression: Compression,
+    allow_dangling_redirects: bool,
+}
+
+impl Default for ZimWriterBuilder {
+    fn default() -> Self {
+        ZimWriterBuilder::new()
+    }
+}
+
+impl ZimWriterBuilder {
+    pub fn new() -> Self {
+        ZimWriterBuilder {
+            cluster_size: DEFAULT_CLUSTER_SIZE,
+            compression: Compression::LZMA2,
+            allow_dangling_redirects: false,
+        }
+    }
+
+    /// Sets the uncompressed size at which the open cluster is closed and a new one started.
+    pub fn cluster_size(mut self, size: u64) -> Self {
+        self.cluster_size = size;
+        self
+    }
+
+    /// Sets the compression of the clusters, LZMA2 by default.
+    pub fn compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Accept redirects whose target has not been added, e.g. for incremental builds.
+    ///
+    /// Such redirects are left out of the written file instead of failing with
+    /// `Error::UnresolvedRedirect`.
+    pub fn allow_dangling_redirects(mut self, allow: bool) -> Self {
+        self.allow_dangling_redirects = allow;
+        self
+    }
+
+    pub fn build(self) -> ZimWriter {
+        ZimWriter {
+            cluster_size: self.cluster_size,
+            compression: self.compression,
+            allow_dangling_redirects: self.allow_dangling_redirects,
+            mime_table: Vec::new(),
+            entries: Vec::new(),
+            urls: HashMap::new(),
+            clusters: Vec::new(),
+            current: ClusterBuilder::new(),
+            main_page: None,
+        }
+    }
+}
+
+impl Default for ZimWriter {
+    fn default() -> Self {
+        ZimWriter::new()
+    }
+}
+
+impl ZimWriter {
+    /// Creates a writer with the default configuration, see `ZimWriterBuilder`.
+    pub fn new() -> Self {
+        ZimWriterBuilder::new().build()
+    }
+
+    /// Number of entries added so far.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds an article with the given content.
+    ///
+    /// `mime` is added to the MIME table if it is not there yet. Returns `Error::DuplicateUrl` if
+    /// `url` is already used in `ns`, and `Error::NullByte` if any of the strings contains a null
+    /// byte, as they are stored null-terminated.
+    pub fn add_article(
+        &mut self,
+        ns: Namespace,
+        url: &str,
+        title: &str,
+        mime: &str,
+        data: &[u8],
+    ) -> Result<()> {
+        check_string(url)?;
+        check_string(title)?;
+        check_string(mime)?;
+        self.check_url(ns, url)?;
+
+        let mime_idx = self.mime_index(mime);
+        let cluster_idx = self.clusters.len() as u32;
+        let blob_idx = self.current.add_blob(data);
+
+        self.push_entry(
+            ns,
+            url,
+            title,
+            WriterTarget::Article(mime_idx, cluster_idx, blob_idx),
+        );
+
+        if self.current.estimated_size() >= self.cluster_size {
+            self.close_cluster()?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds a redirect from `url` in `ns` to `target_url` in `target_ns`.
+    ///
+    /// The target does not need to exist yet, it is resolved when the file is written.
+    pub fn add_redirect(
+        &mut self,
+        ns: Namespace,
+        url: &str,
+        title: &str,
+        target_url: &str,
+        target_ns: Namespace,
+    ) -> Result<()> {
+        check_string(url)?;
+        check_string(title)?;
+        check_string(target_url)?;
+        self.check_url(ns, url)?;
+
+        self.push_entry(
+            ns,
+            url,
+            title,
+            WriterTarget::Redirect(target_ns, target_url.to_string()),
+        );
+
+        Ok(())
+    }
+
+    /// Checks that the target of every redirect has been added.
+    ///
+    /// Returns `Error::UnresolvedRedirect` for the first redirect whose target is missing, unless
+    /// dangling redirects are allowed.
+    pub fn check_redirects(&self) -> Result<()> {
+        if self.allow_dangling_redirects {
+            return Ok(());
+        }
+
+        for entry in &self.entries {
+            if let WriterTarget::Redirect(target_ns, ref target_url) = entry.target {
+                if self.resolve(target_ns, target_url).is_none() {
+                    return Err(Error::UnresolvedRedirect {
+                        from_url: entry.url.clone(),
+                        to_url: target_url.clone(),
+                    });
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns `(from_url, to_url)` for each redirect which will be left out of the file because
+    /// its target is missing. Always empty unless dangling redirects are allowed.
+    pub fn dangling_redirects(&self) -> Vec<(String, String)> {
+        let keep = self.kept_entries();
+        self.entries
+            .iter()
+            .zip(keep)
+            .filter_map(|(entry, keep)| match entry.target {
+                WriterTarget::Redirect(_, ref target_url) if !keep => {
+                    Some((entry.url.clone(), target_url.clone()))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Sets the entry at `url` in `ns` as the main page.
+    ///
+    /// The entry may be added later. If it is missing when the file is written, the file has no
+    /// main page.
+    pub fn set_main_page(&mut self, ns: Namespace, url: &str) {
+        self.main_page = Some((ns, url.to_string()));
+    }
+
+    /// Writes the complete ZIM file to `writer`.
+    ///
+    /// Fails with `Error::UnresolvedRedirect` if a redirect target is missing, see
+    /// `check_redirects`. All positions are known before anything is written, so the file is
+    /// written front to back and its MD5 checksum is computed on the way.
+    pub fn finalize<W: Write>(mut self, writer: W) -> Result<()> {
+        self.check_redirects()?;
+        self.close_cluster()?;
+
+        // entries in URL order, without dangling redirects
+        let keep = self.kept_entries();
+        let mut url_order: Vec<usize> = (0..self.entries.len()).filter(|&i| keep[i]).collect();
+        url_order.sort_by(|&a, &b| self.entries[a].url_key().cmp(&self.entries[b].url_key()));
+
+        let mut url_index = vec![0u32; self.entries.len()];
+        for (pos, &idx) in url_order.iter().enumerate() {
+            url_index[idx] = pos as u32;
+        }
+
+        let mut title_order: Vec<u32> = (0..url_order.len() as u32).collect();
+        title_order.sort_by(|&a, &b| {
+            let a = &self.entries[url_order[a as usize]];
+            let b = &self.entries[url_order[b as usize]];
+            a.title_key().cmp(&b.title_key())
+        });
+
+        let main_page = self
+            .main_page
+            .as_ref()
+            .and_then(|(ns, url)| self.resolve(*ns, url))
+            .filter(|&idx| keep[idx])
+            .map_or(0xffffffff, |idx| url_index[idx]);
+
+        let dir_entries = url_order
+            .iter()
+            .map(|&idx| self.serialize_entry(&self.entries[idx], &url_index))
+            .collect::<Result<Vec<_>>>()?;
+
+        // extended clusters require major version 6
+        let version_major = if self.clusters.iter().any(|c| c[0] & 0x10 != 0) {
+            6
+        } else {
+            5
+        };
+
+        write_zim(
+            FileLayout {
+                version: (version_major, 0),
+                uuid: generate_uuid(self.entries.len()),
+                mime_table: &self.mime_table,
+                dir_entries,
+                title_order,
+                clusters: self.clusters.iter().map(|c| &c[..]).collect(),
+                main_page,
+                // no layout page
+                layout_page: 0xffffffff,
+            },
+            writer,
+        )
+    }
+
+    /// Marks which entries are written: everything except redirects whose target is missing
+    /// (or is such a redirect itself).
+    fn kept_entries(&self) -> Vec<bool> {
+        let mut keep = vec![true; self.entries.len()];
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (idx, entry) in self.entries.iter().enumerate() {
+                if let WriterTarget::Redirect(target_ns, ref target_url) = entry.target {
+                    let resolved = match self.resolve(target_ns, target_url) {
+                        Some(target) => keep[target],
+                        None => false,
+                    };
+                    if keep[idx] && !resolved {
+                        keep[idx] = false;
+                        changed = true;
+                    }
+                }
+            }
+        }
+
+        keep
+    }
+
+    /// Serializes a directory entry, `url_index` maps indices into `entries` to URL indices.
+    fn serialize_entry(&self, entry: &WriterEntry, url_index: &[u32]) -> Result<Vec<u8>> {
+        let (mime, target) = match entry.target {
+            WriterTarget::Article(mime, cluster_idx, blob_idx) => {
+                (mime, Target::Cluster(cluster_idx, blob_idx))
+            }
+            WriterTarget::Redirect(target_ns, ref target_url) => {
+                // only kept redirects are serialized, so the target exists
+                let target = self.resolve(target_ns, target_url).unwrap();
+                (REDIRECT_MIME, Target::Redirect(url_index[target]))
+            }
+        };
+
+        serialize_dir_entry(
+            mime,
+            entry.namespace,
+            0,
+            Some(&target),
+            &entry.url,
+            &entry.title,
+        )
+    }
+
+    /// Returns the index into `entries` of the entry stored at `url` in `ns`.
+    fn resolve(&self, ns: Namespace, url: &str) -> Option<usize> {
+        self.urls.get(&(ns as u8, url.to_string())).cloned()
+    }
+
+    fn check_url(&self, ns: Namespace, url: &str) -> Result<()> {
+        if self.urls.contains_key(&(ns as u8, url.to_string())) {
+            return Err(Error::DuplicateUrl {
+                namespace: ns,
+                url: url.to_string(),
+            });
+        }
+
+        Ok(())
+    }
+
+    fn push_entry(&mut self, ns: Namespace, url: &str, title: &str, target: WriterTarget) {
+        self.urls
+            .insert((ns as u8, url.to_string()), self.entries.len());
+        self.entries.push(WriterEntry {
+            namespace: ns,
+            url: url.to_string(),
+            title: title.to_string(),
+            target,
+        });
+    }
+
+    /// Returns the index of `mime` in the MIME table, registering it if needed.
+    fn mime_index(&mut self, mime: &str) -> u16 {
+        match self.mime_table.iter().position(|m| m == mime) {
+            Some(idx) => idx as u16,
+            None => {
+                self.mime_table.push(mime.to_string());
+                (self.mime_table.len() - 1) as u16
+            }
+        }
+    }
+
+    /// Serializes the open cluster and starts a new one.
+    fn close_cluster(&mut self) -> Result<()> {
+        if self.current.is_empty() {
+            return Ok(());
+        }
+
+        let mut builder = mem::take(&mut self.current);
+        builder.set_compression(self.compression);
+        let mut raw = Vec::new();
+        builder.finish(&mut raw)?;
+        self.clusters.push(raw);
+
+        Ok(())
+    }
+}
+
+impl Zim {
+    /// Writes this ZIM file to `writer`.
+    ///
+    /// The directory entries are serialized again and the clusters are copied as they are. The
+    /// pointer lists are laid out anew, so the header positions may differ from the original
+    /// file, and the checksum is always MD5. The geo index is not written.
+    pub fn write_to<W: Write>(&self, writer: W) -> Result<()> {
+        let dir_entries = self
+            .try_iterate_by_urls()
+            .map(|entry| self.serialize_zim_entry(&entry?))
+            .collect::<Result<Vec<_>>>()?;
+
+        let cluster_count = self.cluster_list.len();
+        let clusters = (0..cluster_count)
+            .map(|idx| {
+                // the last cluster ends where the checksum starts
+                let end = if idx + 1 < cluster_count {
+                    self.cluster_list[idx + 1]
+                } else {
+                    self.header.checksum_pos
+                };
+                file_region(&self.master_view, self.cluster_list[idx], end)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        write_zim(
+            FileLayout {
+                version: (self.header.version_major, self.header.version_minor),
+                uuid: *self.header.uuid.as_bytes(),
+                mime_table: &self.mime_table,
+                dir_entries,
+                title_order: self.article_list.clone(),
+                clusters,
+                main_page: self.header.main_page.unwrap_or(0xffffffff),
+                layout_page: self.header.layout_page.unwrap_or(0xffffffff),
+            },
+            writer,
+        )
+    }
+
+    fn serialize_zim_entry(&self, entry: &DirectoryEntry) -> Result<Vec<u8>> {
+        let mime = match entry.mime_type {
+            MimeType::Redirect => REDIRECT_MIME,
+            MimeType::LinkTarget => LINK_TARGET_MIME,
+            MimeType::DeletedEntry => DELETED_ENTRY_MIME,
+            MimeType::Type(ref mime) => self
+                .mime_table
+                .iter()
+                .position(|m| m == mime)
+                .ok_or(Error::UnknownMimeType)? as u16,
+        };
+
+        serialize_dir_entry(
+            mime,
+            entry.namespace,
+            entry.revision.unwrap_or(0),
+            entry.target.as_ref(),
+            &entry.url,
+            &entry.title,
+        )
+    }
+}
+
+/// Everything needed to write a ZIM file, with the directory entries already serialized.
+struct FileLayout<'a> {
+    /// Major and minor version
+    version: (u16, u16),
+    uuid: [u8; 16],
+    mime_table: &'a [String],
+    /// Serialized directory entries, in URL order
+    dir_entries: Vec<Vec<u8>>,
+    /// URL indices in title order
+    title_order: Vec<u32>,
+    /// Serialized clusters, including the info byte
+    clusters: Vec<&'a [u8]>,
+    main_page: u32,
+    layout_page: u32,
+}
+
+/// Writes a ZIM file front to back, computing the MD5 checksum on the way.
+///
+/// The MIME table directly follows the header, then come the directory entries, the pointer
+/// lists and the clusters.
+fn write_zim<W: Write>(layout: FileLayout<'_>, writer: W) -> Result<()> {
+    let mime_size = layout
+        .mime_table
+        .iter()
+        .map(|mime| mime.len() as u64 + 1)
+        .sum::<u64>()
+        + 1;
+    let dir_size: u64 = layout.dir_entries.iter().map(|e| e.len() as u64).sum();
+    let entry_count = layout.dir_entries.len() as u64;
+    let url_ptr_pos = HEADER_SIZE + mime_size + dir_size;
+    let title_ptr_pos = url_ptr_pos + entry_count * 8;
+    let cluster_ptr_pos = title_ptr_pos + entry_count * 4;
+    let clusters_pos = cluster_ptr_pos + layout.clusters.len() as u64 * 8;
+    let clusters_size: u64 = layout.clusters.iter().map(|c| c.len() as u64).sum();
+    let checksum_pos = clusters_pos + clusters_size;
+
+    let mut out = HashingWriter {
+        inner: writer,
+        hasher: Md5::new(),
+    };
+
+    out.write_u32::<LittleEndian>(ZIM_MAGIC_NUMBER)?;
+    out.write_u16::<LittleEndian>(layout.version.0)?;
+    out.write_u16::<LittleEndian>(layout.version.1)?;
+    out.write_all(&layout.uuid)?;
+    out.write_u32::<LittleEndian>(entry_count as u32)?;
+    out.write_u32::<LittleEndian>(layout.clusters.len() as u32)?;
+    out.write_u64::<LittleEndian>(url_ptr_pos)?;
+    out.write_u64::<LittleEndian>(title_ptr_pos)?;
+    out.write_u64::<LittleEndian>(cluster_ptr_pos)?;
+    out.write_u64::<LittleEndian>(HEADER_SIZE)?;
+    out.write_u32::<LittleEndian>(layout.main_page)?;
+    out.write_u32::<LittleEndian>(layout.layout_page)?;
+    out.write_u64::<LittleEndian>(checksum_pos)?;
+
+    for mime in layout.mime_table {
+        out.write_all(mime.as_bytes())?;
+        out.write_u8(0)?;
+    }
+    out.write_u8(0)?;
+
+    for entry in &layout.dir_entries {
+        out.write_all(entry)?;
+    }
+    let mut dir_entry_pos = HEADER_SIZE + mime_size;
+    for entry in &layout.dir_entries {
+        out.write_u64::<LittleEndian>(dir_entry_pos)?;
+        dir_entry_pos += entry.len() as u64;
+    }
+    for &idx in &layout.title_order {
+        out.write_u32::<LittleEndian>(idx)?;
+    }
+
+    let mut cluster_pos = clusters_pos;
+    for cluster in &layout.clusters {
+        out.write_u64::<LittleEndian>(cluster_pos)?;
+        cluster_pos += cluster.len() as u64;
+    }
+    for cluster in &layout.clusters {
+        out.write_all(cluster)?;
+    }
+
+    let checksum = out.hasher.result();
+    out.inner.write_all(&checksum)?;
+    out.inner.flush()?;
+
+    Ok(())
+}
+
+/// Serializes a directory entry. `target` is `None` for link targets and deleted entries.
+fn serialize_dir_entry(
+    mime: u16,
+    namespace: Namespace,
+    revision: u32,
+    target: Option<&Target>,
+    url: &str,
+    title: &str,
+) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    out.write_u16::<LittleEndian>(mime)?;
+    // no extra parameters
+    out.write_u8(0)?;
+    out.write_u8(namespace as u8)?;
+    out.write_u32::<LittleEndian>(revision)?;
+
+    match target {
+        Some(Target::Cluster(cluster_idx, blob_idx)) => {
+            out.write_u32::<LittleEndian>(*cluster_idx)?;
+            out.write_u32::<LittleEndian>(*blob_idx)?;
+        }
+        Some(Target::Redirect(url_idx)) => out.write_u32::<LittleEndian>(*url_idx)?,
+        None => {}
+    }
+
+    out.write_all(url.as_bytes())?;
+    out.write_u8(0)?;
+    out.write_all(title.as_bytes())?;
+    out.write_u8(0)?;
+
+    Ok(out)
+}
+
+/// Passes writes through to `inner`, while hashing everything written.
+struct HashingWriter<W> {
+    inner: W,
+    hasher: Md5,
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.input(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Derives a UUID from the current time, there is no need for it to be cryptographically random.
+fn generate_uuid(seed: usize) -> [u8; 16] {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+
+    let mut hasher = Md5::new();
+    hasher.input(now.to_le_bytes());
+    hasher.input((seed as u64).to_le_bytes());
+    hasher.input(std::process::id().to_le_bytes());
+
+    let mut uuid = [0u8; 16];
+    uuid.copy_from_slice(&hasher.result());
+    uuid
+}
+
+fn check_string(s: &str) -> Result<()> {
+    if s.contains('\0') {
+        return Err(Error::NullByte(s.to_string()));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_add_article() {
+    let mut writer = ZimWriterBuilder::new().cluster_size(8).build();
+
+    writer
+        .add_article(Namespace::Articles, "Apple", "Apple", "text/html", b"apple")
+        .unwrap();
+    writer
+        .add_article(Namespace::Articles, "Banana", "", "text/html", b"banana")
+        .unwrap();
+    // the same url in another namespace is fine
+    writer
+        .add_article(Namespace::ImagesFile, "Apple", "", "image/png", b"png")
+        .unwrap();
+    assert_eq!(writer.len(), 3);
+    assert_eq!(writer.mime_table, vec!["text/html", "image/png"]);
+    // "apple" + "banana" reach the cluster size
+    assert_eq!(writer.clusters.len(), 1);
+
+    match writer.add_article(Namespace::Articles, "Apple", "", "text/html", b"") {
+        Err(Error::DuplicateUrl { url, .. }) => assert_eq!(url, "Apple"),
+        _ => panic!("expected a duplicate url error"),
+    }
+    match writer.add_article(Namespace::Articles, "a\0b", "", "text/html", b"") {
+        Err(Error::NullByte(_)) => {}
+        _ => panic!("expected a null byte error"),
+    }
+    assert_eq!(writer.len(), 3);
+}
+
+#[test]
+fn test_add_redirect() {
+    let mut writer = ZimWriter::new();
+    writer
+        .add_redirect(
+            Namespace::Articles,
+            "Fruit",
+            "",
+            "Apple",
+            Namespace::Articles,
+        )
+        .unwrap();
+
+    match writer.check_redirects() {
+        Err(Error::UnresolvedRedirect { from_url, to_url }) => {
+            assert_eq!(from_url, "Fruit");
+            assert_eq!(to_url, "Apple");
+        }
+        _ => panic!("expected an unresolved redirect error"),
+    }
+
+    // added after the redirect
+    writer
+        .add_article(Namespace::Articles, "Apple", "", "text/html", b"apple")
+        .unwrap();
+    writer.check_redirects().unwrap();
+
+    let mut writer = ZimWriterBuilder::new()
+        .allow_dangling_redirects(true)
+        .build();
+    writer
+        .add_redirect(
+            Namespace::Articles,
+            "Fruit",
+            "",
+            "Apple",
+            Namespace::Articles,
+        )
+        .unwrap();
+    writer.check_redirects().unwrap();
+}
+
+#[test]
+fn test_finalize() {
+    use std::io::{Cursor, Read};
+
+    use crate::mime_type::MimeType;
+    use crate::target::Target;
+    use crate::zim::Zim;
+
+    let mut writer = ZimWriterBuilder::new().cluster_size(16).build();
+    writer
+        .add_article(Namespace::Articles, "Banana", "", "text/html", b"banana")
+        .unwrap();
+    writer
+        .add_redirect(
+            Namespace::Articles,
+            "Fruit",
+            "",
+            "Apple",
+            Namespace::Articles,
+        )
+        .unwrap();
+    writer
+        .add_article(
+            Namespace::Articles,
+            "Apple",
+            "An apple",
+            "text/html",
+            b"apple",
+        )
+        .unwrap();
+    writer
+        .add_article(
+            Namespace::ImagesFile,
+            "logo.png",
+            "",
+            "image/png",
+            &[0u8; 32],
+        )
+        .unwrap();
+    writer
+        .add_article(Namespace::Metadata, "Title", "", "text/plain", b"Fruits")
+        .unwrap();
+
+    let mut raw = Vec::new();
+    writer.finalize(&mut raw).unwrap();
+
+    let zim = Zim::new_from_reader(Cursor::new(raw)).unwrap();
+    zim.verify_checksum().unwrap();
+    assert_eq!(zim.header.article_count, 5);
+    assert_eq!(zim.header.cluster_count, 2);
+
+    let urls: Vec<String> = zim.iterate_by_urls().map(|entry| entry.url).collect();
+    assert_eq!(urls, vec!["Apple", "Banana", "Fruit", "logo.png", "Title"]);
+
+    let titles: Vec<String> = (0..5)
+        .map(|idx| zim.get_by_title_index(idx).unwrap().url)
+        .collect();
+    assert_eq!(
+        titles,
+        vec!["Apple", "Banana", "Fruit", "logo.png", "Title"]
+    );
+
+    let fruit = zim.get_by_url_index(2).unwrap();
+    assert_eq!(fruit.mime_type, MimeType::Redirect);
+    assert_eq!(fruit.target, Some(Target::Redirect(0)));
+
+    let apple = zim.get_by_url_index(0).unwrap();
+    assert_eq!(apple.title, "An apple");
+    assert_eq!(apple.mime_type, MimeType::Type("text/html".into()));
+    let mut content = Vec::new();
+    zim.open_article_from_entry(&apple)
+        .unwrap()
+        .unwrap()
+        .read_to_end(&mut content)
+        .unwrap();
+    assert_eq!(content, b"apple");
+}
+
+#[test]
+fn test_write_to() {
+    use std::io::Cursor;
+
+    let mut writer = ZimWriterBuilder::new().cluster_size(8).build();
+    writer
+        .add_article(
+            Namespace::Articles,
+            "Apple",
+            "An apple",
+            "text/html",
+            b"apple",
+        )
+        .unwrap();
+    writer
+        .add_article(Namespace::Articles, "Banana", "", "text/html", b"banana")
+        .unwrap();
+    writer
+        .add_redirect(
+            Namespace::Articles,
+            "Fruit",
+            "",
+            "Apple",
+            Namespace::Articles,
+        )
+        .unwrap();
+    writer
+        .add_article(Namespace::Metadata, "Title", "", "text/plain", b"Fruits")
+        .unwrap();
+    writer.set_main_page(Namespace::Articles, "Banana");
+
+    let mut raw = Vec::new();
+    writer.finalize(&mut raw).unwrap();
+    let original = Zim::new_from_reader(Cursor::new(raw)).unwrap();
+
+    let mut rewritten = Vec::new();
+    original.write_to(&mut rewritten).unwrap();
+    let copy = Zim::new_from_reader(Cursor::new(rewritten)).unwrap();
+    copy.verify_checksum().unwrap();
+
+    assert_eq!(copy.header.uuid.as_bytes(), original.header.uuid.as_bytes());
+    assert_eq!(copy.header.article_count, original.header.article_count);
+    assert_eq!(copy.header.cluster_count, original.header.cluster_count);
+    assert_eq!(copy.header.main_page, original.header.main_page);
+    assert_eq!(copy.mime_table, original.mime_table);
+    assert_eq!(copy.article_list, original.article_list);
+
+    let entries = |zim: &Zim| {
+        zim.iterate_by_urls()
+            .map(|entry| format!("{:?}", entry))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(entries(&copy), entries(&original));
+
+    for idx in 0..original.header.cluster_count {
+        let (a, b) = (
+            original.get_cluster(idx).unwrap(),
+            copy.get_cluster(idx).unwrap(),
+        );
+        assert_eq!(a.blob_count().unwrap(), b.blob_count().unwrap());
+        for blob in 0..a.blob_count().unwrap() {
+            let data = a.map_blob(blob, |data| data.to_vec()).unwrap();
+            assert_eq!(b.map_blob(blob, |copy| copy.to_vec()).unwrap(), data);
+        }
+    }
+}
+
+#[test]
+fn test_compression() {
+    use std::io::Cursor;
+
+    let mut writer = ZimWriterBuilder::new()
+        .compression(Compression::None)
+        .build();
+    writer
+        .add_article(Namespace::Articles, "Apple", "", "text/html", b"apple")
+        .unwrap();
+    let mut raw = Vec::new();
+    writer.finalize(&mut raw).unwrap();
+
+    let zim = Zim::new_from_reader(Cursor::new(raw)).unwrap();
+    let cluster = zim.get_cluster(0).unwrap();
+    assert_eq!(cluster.compression(), Compression::None);
+    assert_eq!(cluster.map_blob(0, |blob| blob.to_vec()).unwrap(), b"apple");
+}
@@ -1,30 +1,59 @@
+use std::collections::HashMap;
+use std::fmt;
 use std::fs::File;
+use std::io;
 use std::io::Cursor;
-use std::io::{BufRead, BufReader, Read};
+use std::io::{BufRead, Read};
+use std::ops::{Deref, Range};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::Mutex;
+#[cfg(feature = "tantivy")]
+use std::sync::RwLock;
 
+#[cfg(feature = "bloomfilter")]
+use bloomfilter::Bloom;
 use byteorder::{LittleEndian, ReadBytesExt};
-use md5::{digest::generic_array::GenericArray, Digest, Md5};
-use memmap::Mmap;
+#[cfg(unix)]
+use memmap2::Advice;
+#[cfg(not(target_arch = "wasm32"))]
+use memmap2::Mmap;
+use once_cell::sync::OnceCell;
+use rayon::prelude::*;
 
-use crate::cluster::Cluster;
+use crate::checksum::{compute_checksum, read_checksum, ChecksumType, ChecksumValue};
+use crate::cluster::{Blob, Cluster, DecompressPool};
 use crate::directory_entry::DirectoryEntry;
 use crate::directory_iterator::DirectoryIterator;
 use crate::errors::{Error, Result};
+use crate::geo_index::GeoIndex;
 use crate::mime_type::MimeType;
+use crate::namespace::Namespace;
+use crate::target::Target;
 use crate::uuid::Uuid;
 
 /// Magic number to recognise the file format, must be 72173914
 pub const ZIM_MAGIC_NUMBER: u32 = 72173914;
 
+/// Maximum number of redirects followed by `article_at_url`.
+pub const MAX_REDIRECTS: usize = 32;
+
+/// False positive rate of the URL filter used by `contains_url`.
+#[cfg(feature = "bloomfilter")]
+const URL_FILTER_FP_RATE: f64 = 0.01;
+
 /// Represents a ZIM file
+///
+/// There is no explicit `Drop` impl: fields are dropped in declaration order, so the memory map
+/// is released before anything declared after it. The underlying `File` is closed as soon as the
+/// map has been created in `Zim::new`, so no file handle is held while a `Zim` is alive.
 #[allow(dead_code)]
 pub struct Zim {
     // Zim structure data:
     pub header: ZimHeader,
 
-    pub master_view: Mmap,
-    /// The path to the file.
+    pub master_view: MasterView,
+    /// The path to the file, empty if it was read with `new_from_reader`.
     pub file_path: PathBuf,
 
     /// List of mimetypes used in this ZIM archive
@@ -33,13 +62,114 @@ pub struct Zim {
     pub article_list: Vec<u32>, // a list of indicies into url_list
     pub cluster_list: Vec<u64>, // a list of offsets
 
-    /// MD5 checksum.
-    pub checksum: Checksum,
+    /// Checksum stored at the end of the file.
+    pub checksum: ChecksumValue,
+    checksum_valid: OnceCell<bool>,
+    /// Buffers for decompressing clusters, see `new_with_pool`.
+    pool: Option<Arc<Mutex<DecompressPool>>>,
+    /// URL index per namespace, see `build_url_index`.
+    url_index: OnceCell<HashMap<u8, HashMap<String, u32>>>,
+    /// Whether invalid UTF-8 in URLs and titles is replaced, see `with_lossy_utf8`.
+    lossy_utf8: bool,
+    /// Filter over all namespaces and URLs, built by the first `contains_url` call. `None` if
+    /// some entries could not be read, as they would be missing from the filter.
+    #[cfg(feature = "bloomfilter")]
+    url_filter: OnceCell<Option<Bloom<Vec<u8>>>>,
+
+    /// Full-text search index, see `export_search_index`.
+    #[cfg(feature = "tantivy")]
+    pub(crate) search_index: RwLock<Option<tantivy::Index>>,
+}
+
+// a panic while reading must not leave a `Zim` in an inconsistent state
+static_assertions::assert_impl_all!(Zim: std::panic::UnwindSafe, std::panic::RefUnwindSafe);
+// clusters borrow the file data from a shared `Zim`, so they can be read from several threads
+static_assertions::assert_impl_all!(Zim: Send, Sync);
+
+/// The raw bytes of a ZIM file.
+pub enum MasterView {
+    /// The file is memory mapped.
+    #[cfg(not(target_arch = "wasm32"))]
+    Mmap(Mmap),
+    /// The file has been read into memory, e.g. because it can't be mapped.
+    Bytes(Arc<[u8]>),
+}
+
+impl MasterView {
+    #[cfg(unix)]
+    fn advise(&self, advice: Advice) -> io::Result<()> {
+        match *self {
+            MasterView::Mmap(ref map) if !map.is_empty() => map.advise(advice),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Deref for MasterView {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            #[cfg(not(target_arch = "wasm32"))]
+            MasterView::Mmap(map) => map,
+            MasterView::Bytes(bytes) => bytes,
+        }
+    }
+}
+
+/// A problem with the pointer lists, found by `Zim::check_pointer_monotonicity`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum PointerViolation {
+    /// The URL pointer at `idx` points at or past the checksum.
+    UrlPointerOutOfBounds { idx: u32, offset: u64 },
+    /// The URL pointer at `idx` points to the same entry as the one at `first`.
+    DuplicateUrlPointer { idx: u32, first: u32, offset: u64 },
+    /// The title pointer at `idx` is not a valid URL index.
+    InvalidTitlePointer { idx: u32, url_idx: u32 },
+    /// The entry at URL index `idx` sorts after the one at `next`.
+    UnsortedUrls { idx: u32, next: u32 },
+}
+
+/// The kind of problem found by `Zim::validate`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WarningKind {
+    /// A URL pointer points at or past the checksum.
+    UrlPointerOutOfBounds,
+    /// The directory entry at a URL pointer can't be parsed.
+    InvalidDirectoryEntry,
+    /// A cluster pointer points at or past the checksum.
+    ClusterPointerOutOfBounds,
+    /// A cluster pointer is not larger than the one before it.
+    UnsortedClusterPointer,
+    /// A redirect points past the end of the URL pointer list.
+    RedirectOutOfBounds,
+    /// A MIME type in the MIME type list is not valid UTF-8.
+    InvalidMimeType,
+    /// The main or layout page is not a valid URL index.
+    InvalidPageIndex,
+}
+
+/// A problem found by `Zim::validate`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ValidationWarning {
+    pub kind: WarningKind,
+    /// The URL index of the entry, or the index of the cluster, the problem was found in.
+    pub cluster_or_entry_idx: Option<u32>,
+    pub message: String,
 }
 
-pub type Checksum = GenericArray<u8, <Md5 as Digest>::OutputSize>;
+impl ValidationWarning {
+    fn new(kind: WarningKind, idx: Option<u32>, message: String) -> ValidationWarning {
+        ValidationWarning {
+            kind,
+            cluster_or_entry_idx: idx,
+            message,
+        }
+    }
+}
 
 /// A ZIM file starts with a header.
+#[derive(Debug, Clone)]
 pub struct ZimHeader {
     /// Major version, either 5 or 6
     pub version_major: u16,
@@ -63,22 +193,126 @@ pub struct ZimHeader {
     pub main_page: Option<u32>,
     /// ayout page or 0xffffffffff if no layout page
     pub layout_page: Option<u32>,
-    /// pointer to the md5checksum of this file without the checksum itself.
-    /// This points always 16 bytes before the end of the file.
+    /// pointer to the checksum of this file without the checksum itself.
+    /// This points always 16 (MD5) or 32 (SHA-256) bytes before the end of the file.
     pub checksum_pos: u64,
+    /// algorithm of the checksum, derived from the number of bytes after `checksum_pos`
+    pub checksum_type: ChecksumType,
     /// pointer to the geo index (optional). Present if mimeListPos is at least 80.
     pub geo_index_pos: Option<u64>,
 }
 
+/// A summary of the header, one field per line.
+impl fmt::Display for ZimHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let page = |page: Option<u32>| match page {
+            Some(idx) => idx.to_string(),
+            None => "-".into(),
+        };
+
+        writeln!(f, "Version: {}.{}", self.version_major, self.version_minor)?;
+        writeln!(f, "UUID: {}", self.uuid)?;
+        writeln!(f, "Article Count: {}", self.article_count)?;
+        writeln!(f, "Cluster Count: {}", self.cluster_count)?;
+        writeln!(f, "Main Page Index: {}", page(self.main_page))?;
+        writeln!(f, "Layout Page Index: {}", page(self.layout_page))?;
+        write!(f, "Checksum Pos: {}", self.checksum_pos)
+    }
+}
+
+/// Options for opening a Zim file from a path, e.g.
+/// `ZimOpenOptions::new().mmap_advice(Advice::Sequential).open(path)`.
+#[derive(Clone, Debug, Default)]
+pub struct ZimOpenOptions {
+    #[cfg(unix)]
+    mmap_advice: Option<Advice>,
+}
+
+impl ZimOpenOptions {
+    pub fn new() -> Self {
+        ZimOpenOptions::default()
+    }
+
+    /// Tells the kernel how the memory map will be accessed, e.g. `Advice::Sequential` for bulk
+    /// extraction or `Advice::Random` for serving single articles. Without it the kernel uses
+    /// its default readahead.
+    ///
+    /// The advice is ignored if the file is read into memory instead of being mapped.
+    #[cfg(unix)]
+    pub fn mmap_advice(mut self, advice: Advice) -> Self {
+        self.mmap_advice = Some(advice);
+        self
+    }
+
+    /// Loads the Zim file at `p`, like `Zim::new`.
+    pub fn open<P: AsRef<Path>>(&self, p: P) -> Result<Zim> {
+        let f = File::open(p.as_ref())?;
+        #[cfg(not(target_arch = "wasm32"))]
+        let master_view = map_file(f, |f| unsafe { Mmap::map(f) })?;
+        #[cfg(target_arch = "wasm32")]
+        let master_view = read_file(f)?;
+
+        #[cfg(unix)]
+        if let Some(advice) = self.mmap_advice {
+            master_view.advise(advice)?;
+        }
+
+        Zim::from_view(master_view, p.as_ref().into())
+    }
+}
+
 impl Zim {
     /// Loads a Zim file
     ///
     /// Loads a Zim file and parses the header, and the url, title, and cluster offset tables.  The
     /// rest of the data isn't parsed until it's needed, so this should be fairly quick.
+    ///
+    /// If the file system does not support memory mapping (as is the case for some network file
+    /// systems), the file is read into memory instead. On wasm32 it is always read into memory.
     pub fn new<P: AsRef<Path>>(p: P) -> Result<Zim> {
-        let f = File::open(p.as_ref())?;
-        let master_view = unsafe { Mmap::map(&f)? };
+        ZimOpenOptions::new().open(p)
+    }
+
+    /// Loads a Zim file and passes `advice` about the access pattern to the memory map, see
+    /// `ZimOpenOptions::mmap_advice`.
+    #[cfg(unix)]
+    pub fn open_with_advise<P: AsRef<Path>>(p: P, advice: Advice) -> Result<Zim> {
+        ZimOpenOptions::new().mmap_advice(advice).open(p)
+    }
+
+    /// Loads a Zim file, taking the buffers for decompressed clusters from `pool`.
+    ///
+    /// Buffers are given back once a cluster is dropped, which avoids allocating new ones when
+    /// many clusters are read one after another.
+    pub fn new_with_pool<P: AsRef<Path>>(p: P, pool: Arc<Mutex<DecompressPool>>) -> Result<Zim> {
+        let mut zim = Zim::new(p)?;
+        zim.pool = Some(pool);
+        Ok(zim)
+    }
+
+    /// Loads a Zim file and builds its URL index, see `build_url_index`.
+    pub fn new_with_url_index<P: AsRef<Path>>(p: P) -> Result<Zim> {
+        let zim = Zim::new(p)?;
+        zim.build_url_index()?;
+        Ok(zim)
+    }
+
+    /// Loads a Zim file by reading all of `reader` into memory.
+    pub fn new_from_reader<R: Read>(mut reader: R) -> Result<Zim> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+
+        Zim::open_from_bytes(data.into())
+    }
+
+    /// Loads a Zim file which is already in memory, e.g. embedded with `include_bytes!`.
+    ///
+    /// The data is shared, not copied.
+    pub fn open_from_bytes(data: Arc<[u8]>) -> Result<Zim> {
+        Zim::from_view(MasterView::Bytes(data), PathBuf::new())
+    }
 
+    fn from_view(master_view: MasterView, file_path: PathBuf) -> Result<Zim> {
         let (header, mime_table) = parse_header(&master_view)?;
 
         let url_list = parse_url_list(&master_view, header.url_ptr_pos, header.article_count)?;
@@ -88,38 +322,144 @@ impl Zim {
         let cluster_list =
             parse_cluster_list(&master_view, header.cluster_ptr_pos, header.cluster_count)?;
 
-        let checksum = read_checksum(&master_view, header.checksum_pos)?;
+        let checksum = read_checksum(&master_view, header.checksum_pos, header.checksum_type)?;
 
         Ok(Zim {
             header,
-            file_path: p.as_ref().into(),
+            file_path,
             master_view,
             mime_table,
             url_list,
             article_list,
             cluster_list,
             checksum,
+            checksum_valid: OnceCell::new(),
+            pool: None,
+            url_index: OnceCell::new(),
+            lossy_utf8: false,
+            #[cfg(feature = "bloomfilter")]
+            url_filter: OnceCell::new(),
+            #[cfg(feature = "tantivy")]
+            search_index: RwLock::new(None),
         })
     }
 
-    /// Get the number of articles.
+    /// Replaces invalid UTF-8 sequences in URLs and titles with U+FFFD when reading entries.
+    ///
+    /// By default such entries fail to parse with `Error::Utf8`, so `iterate_by_urls` skips them.
+    pub fn with_lossy_utf8(mut self) -> Zim {
+        self.lossy_utf8 = true;
+        self
+    }
+
+    /// Get the number of articles, across all namespaces.
+    ///
+    /// See `count_by_namespace` for the number of articles in a single namespace.
     pub fn article_count(&self) -> usize {
         self.article_list.len()
     }
 
+    /// An owned copy of the header, which can outlive this `Zim` or be sent to other threads.
+    pub fn clone_header(&self) -> ZimHeader {
+        self.header.clone()
+    }
+
+    /// Size of the file in bytes.
+    pub fn file_size(&self) -> u64 {
+        self.master_view.len() as u64
+    }
+
+    /// Combined size of all clusters as stored in the file, i.e. after compression.
+    pub fn total_compressed_size(&self) -> u64 {
+        // the last cluster ends where the checksum starts
+        let ends = self
+            .cluster_list
+            .iter()
+            .skip(1)
+            .chain(std::iter::once(&self.header.checksum_pos));
+        self.cluster_list
+            .iter()
+            .zip(ends)
+            .map(|(start, end)| end.saturating_sub(*start))
+            .sum()
+    }
+
+    /// Size of everything but the clusters and the checksum: the header, MIME type list,
+    /// pointer lists and directory entries.
+    pub fn metadata_overhead_bytes(&self) -> u64 {
+        self.file_size()
+            .saturating_sub(self.total_compressed_size())
+            .saturating_sub(self.header.checksum_type.digest_len() as u64)
+    }
+
     /// Computes the checksum, and returns an error if it does not match the one in
     /// the file.
+    ///
+    /// The outcome is cached, so only the first call reads the whole file.
     pub fn verify_checksum(&self) -> Result<()> {
-        let checksum_computed = compute_checksum(&self.file_path, self.header.checksum_pos)?;
+        let valid = match self.checksum_valid.get() {
+            Some(&valid) => valid,
+            None => {
+                let (pos, checksum_type) = (self.header.checksum_pos, self.header.checksum_type);
+                let checksum_computed = compute_checksum(&self.master_view, pos, checksum_type)?;
+
+                *self
+                    .checksum_valid
+                    .get_or_init(|| self.checksum == checksum_computed)
+            }
+        };
 
-        if self.checksum != checksum_computed {
+        if !valid {
             return Err(Error::InvalidChecksum);
         }
 
         Ok(())
     }
 
+    /// Returns the cached result of `verify_checksum`, or `None` if it has not completed yet.
+    pub fn checksum_verified(&self) -> Option<bool> {
+        self.checksum_valid.get().cloned()
+    }
+
+    /// Hints to the kernel that the file will be read sequentially, e.g. for bulk extraction.
+    ///
+    /// This is a no-op if the file is not memory mapped or the platform has no `madvise`.
+    pub fn advise_sequential(&self) -> Result<()> {
+        #[cfg(unix)]
+        self.master_view.advise(Advice::Sequential)?;
+        Ok(())
+    }
+
+    /// Hints to the kernel that the file will be read in random order, e.g. when serving it.
+    ///
+    /// This is a no-op if the file is not memory mapped or the platform has no `madvise`.
+    pub fn advise_random(&self) -> Result<()> {
+        #[cfg(unix)]
+        self.master_view.advise(Advice::Random)?;
+        Ok(())
+    }
+
+    /// Iterates over the MIME types in the MIME table, in index order.
+    ///
+    /// Each MIME type is listed only once.
+    pub fn mime_types(&self) -> impl Iterator<Item = &str> {
+        self.mime_table.iter().map(|mime| mime.as_str())
+    }
+
+    /// Number of entries in the MIME table.
+    pub fn mime_type_count(&self) -> usize {
+        self.mime_table.len()
+    }
+
+    /// Whether `mime` is in the MIME table.
+    pub fn has_mime_type(&self, mime: &str) -> bool {
+        self.mime_types().any(|m| m == mime)
+    }
+
     /// Indexes into the ZIM mime_table.
+    ///
+    /// A few values are not indices but mark special entries: `0xffff` is a redirect, `0xfffe` a
+    /// link target and `0xfffd` a deleted entry. Returns `None` for indices outside the table.
     pub fn get_mimetype(&self, id: u16) -> Option<MimeType> {
         match id {
             0xffff => Some(MimeType::Redirect),
@@ -138,213 +478,2054 @@ impl Zim {
 
     /// Iterates over articles, sorted by URL.
     ///
-    /// For performance reasons, you might want to extract by cluster instead.
+    /// For performance reasons, you might want to extract by cluster instead. A parallel version
+    /// is available through rayon's `par_iter()` on `&Zim`, which yields a `Result` for every
+    /// URL index instead of skipping broken entries.
     pub fn iterate_by_urls(&self) -> DirectoryIterator {
         DirectoryIterator::new(self)
     }
 
+    /// Iterates over articles, sorted by URL, reporting entries that fail to parse.
+    ///
+    /// Unlike `iterate_by_urls`, which silently skips broken entries, every URL index yields
+    /// either the parsed `DirectoryEntry` or the error encountered (e.g. `Error::InvalidNamespace`).
+    pub fn try_iterate_by_urls(&self) -> impl Iterator<Item = Result<DirectoryEntry>> + '_ {
+        (0..self.header.article_count).map(move |idx| self.get_by_url_index(idx))
+    }
+
+    /// Iterates over the articles at URL indices `start..end`, sorted by URL.
+    ///
+    /// `end` is clamped to `article_count`. Combined with `partition_url_ranges` this allows
+    /// splitting the work over several workers.
+    pub fn iter_articles_in_url_range(
+        &self,
+        start: u32,
+        end: u32,
+    ) -> impl Iterator<Item = Result<DirectoryEntry>> + '_ {
+        let end = end.min(self.header.article_count);
+        (start..end).map(move |idx| self.get_by_url_index(idx))
+    }
+
+    /// Divides the URL indices `0..article_count` into `n` contiguous ranges of roughly equal size.
+    ///
+    /// Returns no ranges if `n` is 0.
+    pub fn partition_url_ranges(&self, n: u32) -> Vec<Range<u32>> {
+        if n == 0 {
+            return Vec::new();
+        }
+
+        let count = self.header.article_count;
+        let size = count / n;
+        let remainder = count % n;
+
+        let mut start = 0;
+        (0..n)
+            .map(|i| {
+                // the first `remainder` ranges take one extra entry each
+                let end = start + size + if i < remainder { 1 } else { 0 };
+                let range = start..end;
+                start = end;
+                range
+            })
+            .collect()
+    }
+
     /// Returns the `DirectoryEntry` for the article found at the given URL index.
     ///
-    /// idx must be between 0 and `article_count`
+    /// Returns `Error::InvalidEntryIndex` unless idx is between 0 and `article_count`.
     pub fn get_by_url_index(&self, idx: u32) -> Result<DirectoryEntry> {
-        let entry_offset = self.url_list[idx as usize] as usize;
-        let (_, dir_view) = self.master_view.split_at(entry_offset);
+        let entry_offset = *self
+            .url_list
+            .get(idx as usize)
+            .ok_or(Error::InvalidEntryIndex {
+                idx,
+                max: self.url_list.len() as u32,
+            })? as usize;
+        let dir_view = self
+            .master_view
+            .get(entry_offset..)
+            .ok_or(Error::OutOfBounds)?;
 
-        DirectoryEntry::new(self, dir_view)
+        let mut entry = self.parse_entry(dir_view)?;
+        entry.url_index = Some(idx);
+        Ok(entry)
     }
 
-    /// Returns the given `Cluster`
+    /// Returns the entries at the given URL indices, in the same order.
     ///
-    /// idx must be between 0 and `cluster_count`
-    pub fn get_cluster(&self, idx: u32) -> Result<Cluster> {
-        Cluster::new(
-            &self.master_view,
-            &self.cluster_list,
-            idx,
-            self.header.checksum_pos,
-            self.header.version_major,
-        )
+    /// Each index gets its own result, so a broken entry or invalid index doesn't fail the whole
+    /// batch.
+    pub fn get_n_entries(&self, indices: &[u32]) -> Vec<Result<DirectoryEntry>> {
+        indices
+            .iter()
+            .map(|&idx| self.get_by_url_index(idx))
+            .collect()
     }
-}
 
-fn is_defined(val: u32) -> Option<u32> {
-    if val == 0xffffffff {
-        None
-    } else {
-        Some(val)
+    /// Parses the directory entry at the start of `s`, lossy if `with_lossy_utf8` was called.
+    pub(crate) fn parse_entry(&self, s: &[u8]) -> Result<DirectoryEntry> {
+        if self.lossy_utf8 {
+            DirectoryEntry::new_lossy(self, s)
+        } else {
+            DirectoryEntry::new(self, s)
+        }
     }
-}
 
-fn parse_header(master_view: &Mmap) -> Result<(ZimHeader, Vec<String>)> {
-    let mut header_cur = Cursor::new(master_view);
+    /// Returns the `DirectoryEntry` for the article found at the given title index.
+    ///
+    /// Returns `Error::InvalidEntryIndex` unless idx is between 0 and `article_count`.
+    pub fn get_by_title_index(&self, idx: u32) -> Result<DirectoryEntry> {
+        let url_idx = *self
+            .article_list
+            .get(idx as usize)
+            .ok_or(Error::InvalidEntryIndex {
+                idx,
+                max: self.article_list.len() as u32,
+            })?;
+        self.get_by_url_index(url_idx)
+    }
 
-    let magic = header_cur.read_u32::<LittleEndian>()?;
+    /// Returns all entries grouped by namespace, in namespace byte order, and sorted by title
+    /// within each namespace.
+    ///
+    /// The title pointer list should already be in this order, but it is not relied upon. The
+    /// entries are read up front; errors from entries which fail to parse are yielded first.
+    pub fn articles_by_namespace_then_title(&self) -> impl Iterator<Item = Result<DirectoryEntry>> {
+        let mut entries = Vec::with_capacity(self.article_list.len());
+        let mut errors = Vec::new();
+        for idx in 0..self.article_list.len() as u32 {
+            match self.get_by_title_index(idx) {
+                Ok(entry) => entries.push(entry),
+                Err(err) => errors.push(Err(err)),
+            }
+        }
 
-    if magic != ZIM_MAGIC_NUMBER {
-        return Err(Error::InvalidMagicNumber);
-    }
+        // stable, and close to linear for input which is already sorted
+        entries.sort_by(|a, b| {
+            (a.namespace as u8, entry_title(a)).cmp(&(b.namespace as u8, entry_title(b)))
+        });
 
-    let version_major = header_cur.read_u16::<LittleEndian>()?;
-    if version_major != 5 && version_major != 6 {
-        return Err(Error::InvalidVersion);
+        errors.into_iter().chain(entries.into_iter().map(Ok))
     }
 
-    let version_minor = header_cur.read_u16::<LittleEndian>()?;
+    /// Returns the number of entries in `namespace`.
+    ///
+    /// The URL pointer list is sorted by namespace first, so this binary searches for the first
+    /// entries of `namespace` and of the following namespaces, rather than reading every entry.
+    ///
+    /// The search reads the entries it visits, and an entry which can't be read gives no way to
+    /// tell which side of the boundary it is on. The error is returned instead of a count that
+    /// could be silently wrong.
+    pub fn count_by_namespace(&self, namespace: Namespace) -> Result<usize> {
+        let (start, end) = self.url_namespace_range(namespace)?;
+        Ok((end - start) as usize)
+    }
 
-    let mut uuid = [0u8; 16];
-    for i in 0..16 {
-        uuid[i] = header_cur.read_u8()?;
+    /// Returns a random entry of the `A` namespace, or of the `C` namespace in files using the
+    /// unified namespace, following redirects.
+    #[cfg(feature = "rand")]
+    pub fn get_random_article(&self) -> Result<DirectoryEntry> {
+        if self.has_unified_namespace() {
+            self.get_random_entry(Namespace::Content)
+        } else {
+            self.get_random_entry(Namespace::Articles)
+        }
     }
 
-    let article_count = header_cur.read_u32::<LittleEndian>()?;
-    let cluster_count = header_cur.read_u32::<LittleEndian>()?;
-    let url_ptr_pos = header_cur.read_u64::<LittleEndian>()?;
-    let title_ptr_pos = header_cur.read_u64::<LittleEndian>()?;
-    let cluster_ptr_pos = header_cur.read_u64::<LittleEndian>()?;
-    let mime_list_pos = header_cur.read_u64::<LittleEndian>()?;
+    /// Returns a random entry of `namespace`, following redirects.
+    ///
+    /// Returns `Error::EmptyNamespace` if there are no entries in `namespace`.
+    #[cfg(feature = "rand")]
+    pub fn get_random_entry(&self, namespace: Namespace) -> Result<DirectoryEntry> {
+        use rand::Rng;
 
-    let main_page = header_cur.read_u32::<LittleEndian>()?;
-    let layout_page = header_cur.read_u32::<LittleEndian>()?;
-    let checksum_pos = header_cur.read_u64::<LittleEndian>()?;
+        let (start, end) = self.url_namespace_range(namespace)?;
+        if start == end {
+            return Err(Error::EmptyNamespace);
+        }
 
-    if header_cur.position() != 80 {
-        return Err(Error::InvalidHeader);
+        let idx = rand::thread_rng().gen_range(start..end);
+        self.follow_redirects(self.get_by_url_index(idx)?)
     }
 
-    let geo_index_pos = if mime_list_pos > 80 {
-        Some(header_cur.read_u64::<LittleEndian>()?)
-    } else {
-        None
-    };
+    /// Returns the range of URL indices of the entries in `namespace`.
+    fn url_namespace_range(&self, namespace: Namespace) -> Result<(u32, u32)> {
+        let ns = namespace as u8;
+        let start = self.url_namespace_start(ns)?;
+        let end = match ns.checked_add(1) {
+            Some(next) => self.url_namespace_start(next)?,
+            None => self.url_list.len() as u32,
+        };
 
-    // the mime table is always directly after the 80-byte header, so we'll keep
-    // using our header cursor
-    let mime_table = {
-        let mut mime_table = Vec::new();
-        loop {
-            let mut mime_buf = Vec::new();
-            if let Ok(size) = header_cur.read_until(0, &mut mime_buf) {
-                if size <= 1 {
-                    break;
-                }
-                mime_buf.truncate(size - 1);
-                mime_table.push(String::from_utf8(mime_buf)?);
-            }
-        }
-        mime_table
-    };
+        Ok((start, end))
+    }
 
-    Ok((
-        ZimHeader {
-            version_major,
-            version_minor,
-            uuid: Uuid::new(uuid),
-            article_count,
-            cluster_count,
-            url_ptr_pos,
-            title_ptr_pos,
-            cluster_ptr_pos,
-            mime_list_pos,
-            main_page: is_defined(main_page),
-            layout_page: is_defined(layout_page),
-            checksum_pos,
-            geo_index_pos,
-        },
-        mime_table,
-    ))
-}
+    /// Whether the header points to a geo index.
+    pub fn has_geo_index(&self) -> bool {
+        self.header.geo_index_pos.is_some()
+    }
 
-/// Parses the URL Pointer List.
-/// See https://wiki.openzim.org/wiki/ZIM_file_format#URL_Pointer_List_.28urlPtrPos.29
-fn parse_url_list(master_view: &Mmap, ptr_pos: u64, count: u32) -> Result<Vec<u64>> {
-    let start = ptr_pos as usize;
-    let end = (ptr_pos + count as u64 * 8) as usize;
-    let list_view = master_view.get(start..end).ok_or(Error::OutOfBounds)?;
-    let mut cur = Cursor::new(list_view);
+    /// Returns the geo index, or `None` if the header doesn't point to one, or points outside
+    /// of the file.
+    pub fn geo_index(&self) -> Option<GeoIndex<'_>> {
+        let start = self.header.geo_index_pos?;
+        let header = &self.header;
+        // the index ends where the next part of the file starts
+        let end = [
+            header.mime_list_pos,
+            header.url_ptr_pos,
+            header.title_ptr_pos,
+            header.cluster_ptr_pos,
+            header.checksum_pos,
+        ]
+        .iter()
+        .chain(self.cluster_list.first())
+        .cloned()
+        .filter(|&pos| pos > start)
+        .min()
+        .unwrap_or(self.master_view.len() as u64);
 
-    let mut out: Vec<u64> = Vec::new();
-    for _ in 0..count {
-        out.push(cur.read_u64::<LittleEndian>()?);
+        let raw = self.master_view.get(start as usize..end as usize)?;
+        Some(GeoIndex::new(raw))
     }
 
-    Ok(out)
-}
+    /// Whether the file contains a full text index, i.e. entries in the `X` namespace.
+    pub fn has_full_text_index(&self) -> bool {
+        matches!(self.count_by_namespace(Namespace::FulltextIndex), Ok(count) if count > 0)
+    }
 
-fn parse_article_list(master_view: &Mmap, ptr_pos: u64, count: u32) -> Result<Vec<u32>> {
-    let start = ptr_pos as usize;
-    let end = (ptr_pos as u32 + count * 4) as usize;
-    let list_view = master_view.get(start..end).ok_or(Error::OutOfBounds)?;
+    /// Whether the file puts its content into the unified `C` namespace, as files written by
+    /// openzim-creator 2.0 and later do, rather than into `A`, `I` and `J`.
+    pub fn has_unified_namespace(&self) -> bool {
+        matches!(self.count_by_namespace(Namespace::Content), Ok(count) if count > 0)
+    }
 
-    let mut cur = Cursor::new(list_view);
-    let mut out: Vec<u32> = Vec::new();
+    /// Iterates over the entries in the `A` and `C` namespaces, sorted by URL.
+    ///
+    /// Files use one or the other, so this returns the articles with either namespace scheme.
+    pub fn articles(&self) -> Result<impl Iterator<Item = Result<DirectoryEntry>> + '_> {
+        let (start, end) = self.url_namespace_range(Namespace::Articles)?;
+        let (content_start, content_end) = self.url_namespace_range(Namespace::Content)?;
 
-    for _ in 0..count {
-        out.push(cur.read_u32::<LittleEndian>()?);
+        Ok((start..end)
+            .chain(content_start..content_end)
+            .map(move |idx| self.get_by_url_index(idx)))
     }
 
-    Ok(out)
-}
-
-fn parse_cluster_list(master_view: &Mmap, ptr_pos: u64, count: u32) -> Result<Vec<u64>> {
-    let start = ptr_pos as usize;
-    let end = (ptr_pos as u32 + count * 8) as usize;
-    let cluster_list_view = master_view.get(start..end).ok_or(Error::OutOfBounds)?;
+    /// Returns the URL index of the first entry whose namespace is `ns` or comes after it.
+    fn url_namespace_start(&self, ns: u8) -> Result<u32> {
+        lower_bound(self.url_list.len() as u32, |idx| {
+            Ok((self.get_by_url_index(idx)?.namespace as u8) < ns)
+        })
+    }
 
-    let mut cluster_cur = Cursor::new(cluster_list_view);
-    let mut out: Vec<u64> = Vec::new();
-    for _ in 0..count {
-        out.push(cluster_cur.read_u64::<LittleEndian>()?);
+    /// Returns the entries in `namespace` whose title starts with `prefix`, in title order.
+    ///
+    /// At most `limit` entries are returned. The comparison is case-sensitive, matching the
+    /// order of the title pointer list, so this is a binary search followed by a short scan.
+    pub fn search_title_prefix(
+        &self,
+        prefix: &str,
+        namespace: Namespace,
+        limit: usize,
+    ) -> Result<Vec<DirectoryEntry>> {
+        self.search_prefix(
+            self.article_list.len() as u32,
+            |idx| self.get_by_title_index(idx),
+            entry_title,
+            prefix,
+            namespace,
+            limit,
+        )
     }
-    Ok(out)
-}
 
-/// Read out the the 16 byte long MD5 checksum.
-fn read_checksum(master_view: &Mmap, checksum_pos: u64) -> Result<Checksum> {
-    match master_view.get(checksum_pos as usize..checksum_pos as usize + 16) {
-        Some(raw) => {
-            let mut arr = GenericArray::default();
-            arr.copy_from_slice(raw);
+    /// Case-insensitive version of `search_title_prefix`.
+    ///
+    /// Lowercased titles are not sorted, so this scans every entry of `namespace`.
+    pub fn search_title_prefix_ci(
+        &self,
+        prefix: &str,
+        namespace: Namespace,
+        limit: usize,
+    ) -> Result<Vec<DirectoryEntry>> {
+        let count = self.article_list.len() as u32;
+        let ns = namespace as u8;
+        let prefix = prefix.to_lowercase();
+
+        let start = lower_bound(count, |idx| {
+            Ok((self.get_by_title_index(idx)?.namespace as u8) < ns)
+        })?;
+
+        let mut out = Vec::new();
+        for idx in start..count {
+            if out.len() >= limit {
+                break;
+            }
 
-            Ok(arr)
+            let entry = self.get_by_title_index(idx)?;
+            if entry.namespace as u8 != ns {
+                break;
+            }
+            if entry_title(&entry).to_lowercase().starts_with(&prefix) {
+                out.push(entry);
+            }
         }
-        None => Err(Error::MissingChecksum),
+
+        Ok(out)
     }
-}
 
-/// Compute the MD5 checksum of the file.
-fn compute_checksum(path: &Path, checksum_pos: u64) -> Result<Checksum> {
-    let file = File::open(path)?;
-    let mut reader = BufReader::new(file.take(checksum_pos));
-    let mut buffer = vec![0u8; 1024];
-    let mut hasher = Md5::new();
+    /// Returns all entries whose title starts with `prefix`, ordered by namespace and title.
+    ///
+    /// Searches `namespace`, or all namespaces if it is `None`. See `search_title_prefix` and
+    /// `search_title_prefix_ci` for the cost of the search.
+    pub fn title_prefix_matches(
+        &self,
+        prefix: &str,
+        namespace: Option<Namespace>,
+        case_insensitive: bool,
+    ) -> Result<Vec<DirectoryEntry>> {
+        let namespaces = match namespace {
+            Some(namespace) => vec![namespace],
+            None => Namespace::ALL.to_vec(),
+        };
 
-    loop {
-        let read = reader.read(&mut buffer)?;
-        if read == 0 {
-            break;
+        let mut out = Vec::new();
+        for namespace in namespaces {
+            if case_insensitive {
+                out.extend(self.search_title_prefix_ci(prefix, namespace, usize::MAX)?);
+            } else {
+                out.extend(self.search_title_prefix(prefix, namespace, usize::MAX)?);
+            }
         }
 
-        hasher.input(&buffer[..read]);
+        Ok(out)
     }
 
-    Ok(hasher.result())
-}
+    /// Returns the entries in `namespace` whose URL starts with `prefix`, in URL order.
+    ///
+    /// At most `limit` entries are returned.
+    pub fn search_url_prefix(
+        &self,
+        prefix: &str,
+        namespace: Namespace,
+        limit: usize,
+    ) -> Result<Vec<DirectoryEntry>> {
+        self.search_prefix(
+            self.url_list.len() as u32,
+            |idx| self.get_by_url_index(idx),
+            |entry| &entry.url,
+            prefix,
+            namespace,
+            limit,
+        )
+    }
 
-#[test]
-fn test_zim() {
-    let zim = Zim::new("fixtures/wikipedia_ab_all_2017-03.zim").expect("failed to parse fixture");
+    /// Binary searches a pointer list sorted by `(namespace, key)` for the first entry matching
+    /// `prefix`, then collects entries until the prefix no longer matches.
+    fn search_prefix<G, K>(
+        &self,
+        count: u32,
+        get: G,
+        key: K,
+        prefix: &str,
+        namespace: Namespace,
+        limit: usize,
+    ) -> Result<Vec<DirectoryEntry>>
+    where
+        G: Fn(u32) -> Result<DirectoryEntry>,
+        K: Fn(&DirectoryEntry) -> &str,
+    {
+        let ns = namespace as u8;
+        let start = lower_bound(count, |idx| {
+            let entry = get(idx)?;
+            Ok((entry.namespace as u8, key(&entry)) < (ns, prefix))
+        })?;
 
-    assert_eq!(zim.header.version_major, 5);
-    let cl0 = zim.get_cluster(0).unwrap();
-    assert_eq!(&cl0.get_blob(0).unwrap()[..], &[97, 98, 107][..]);
+        let mut out = Vec::new();
+        for idx in start..count {
+            if out.len() >= limit {
+                break;
+            }
 
-    let cl1 = zim.get_cluster(zim.header.cluster_count - 1).unwrap();
-    let b = cl1.get_blob(0).unwrap();
-    assert_eq!(&b[0..10], &[71, 73, 70, 56, 57, 97, 44, 1, 150, 0]);
-    assert_eq!(
-        &b[b.len() - 10..],
-        &[222, 192, 21, 240, 155, 91, 65, 0, 0, 59]
-    );
+            let entry = get(idx)?;
+            if entry.namespace as u8 != ns || !key(&entry).starts_with(prefix) {
+                break;
+            }
+            out.push(entry);
+        }
 
-    assert_eq!(zim.iterate_by_urls().count(), 3111);
+        Ok(out)
+    }
+
+    /// Returns the `DirectoryEntry` at `url` in `namespace`, found by binary search.
+    ///
+    /// URLs are unique within a namespace, but corrupt files may contain duplicates. In that case
+    /// the entry with the lexicographically smallest title is returned, so the result does not
+    /// depend on where the search happens to land.
+    pub fn get_by_url(&self, namespace: Namespace, url: &str) -> Result<Option<DirectoryEntry>> {
+        match self.article_index_for_url(url, namespace)? {
+            Some(idx) => Ok(Some(self.get_by_url_index(idx)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Returns the URL index of the entry at `url` in `ns`, as found by `get_by_url`.
+    ///
+    /// Only the namespace, URL and title of the entries visited by the binary search are read,
+    /// they are not parsed into a `DirectoryEntry`.
+    pub fn article_index_for_url(&self, url: &str, ns: Namespace) -> Result<Option<u32>> {
+        let ns = ns as u8;
+        if let Some(index) = self.url_index.get() {
+            return Ok(index.get(&ns).and_then(|urls| urls.get(url)).cloned());
+        }
+
+        let url = url.as_bytes();
+        let count = self.url_list.len() as u32;
+        let start = lower_bound(count, |idx| {
+            let key = self.entry_key(idx)?;
+            Ok((key.namespace, key.url) < (ns, url))
+        })?;
+
+        let mut found: Option<(u32, &[u8])> = None;
+        for idx in start..count {
+            let key = self.entry_key(idx)?;
+            if key.namespace != ns || key.url != url {
+                break;
+            }
+            let smaller = match found {
+                Some((_, title)) => key.title < title,
+                None => true,
+            };
+            if smaller {
+                found = Some((idx, key.title));
+            }
+        }
+
+        Ok(found.map(|(idx, _)| idx))
+    }
+
+    /// Returns the title index of the first entry with `title` in `ns`.
+    ///
+    /// Entries without a title are found by their URL, as in the title pointer list. Like
+    /// `article_index_for_url`, the entries are not parsed.
+    pub fn title_index_for_title(&self, title: &str, ns: Namespace) -> Result<Option<u32>> {
+        let ns = ns as u8;
+        let title = title.as_bytes();
+        let title_key = |idx: u32| -> Result<(u8, &[u8])> {
+            let url_idx = *self
+                .article_list
+                .get(idx as usize)
+                .ok_or(Error::InvalidEntryIndex {
+                    idx,
+                    max: self.article_list.len() as u32,
+                })?;
+            let key = self.entry_key(url_idx)?;
+            Ok((key.namespace, key.sort_title()))
+        };
+
+        let count = self.article_list.len() as u32;
+        let idx = lower_bound(count, |idx| Ok(title_key(idx)? < (ns, title)))?;
+        if idx < count && title_key(idx)? == (ns, title) {
+            Ok(Some(idx))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Reads the namespace, URL and title of the entry at URL index `idx`, without parsing the
+    /// rest of it.
+    fn entry_key(&self, idx: u32) -> Result<EntryKey<'_>> {
+        let offset = *self
+            .url_list
+            .get(idx as usize)
+            .ok_or(Error::InvalidEntryIndex {
+                idx,
+                max: self.url_list.len() as u32,
+            })? as usize;
+        let view = self.master_view.get(offset..).ok_or(Error::OutOfBounds)?;
+        let mime_id = view.get(0..2).ok_or(Error::OutOfBounds)?;
+        let namespace = *view.get(3).ok_or(Error::OutOfBounds)?;
+
+        // the strings follow the fields which differ for redirects and entries without content
+        let url_start = match u16::from_le_bytes([mime_id[0], mime_id[1]]) {
+            0xffff => 12,
+            0xfffe | 0xfffd => 8,
+            _ => 16,
+        };
+        let mut strings = view
+            .get(url_start..)
+            .ok_or(Error::OutOfBounds)?
+            .splitn(3, |&b| b == 0);
+        let url = strings.next().ok_or(Error::OutOfBounds)?;
+        let title = strings.next().ok_or(Error::OutOfBounds)?;
+
+        Ok(EntryKey {
+            namespace,
+            url,
+            title,
+        })
+    }
+
+    /// Whether there is an entry at `url` in `namespace`.
+    ///
+    /// With the `bloomfilter` feature, a filter over all URLs is built on the first call. URLs
+    /// which are not in the file are then usually rejected without searching the URL list.
+    pub fn contains_url(&self, url: &str, namespace: Namespace) -> bool {
+        #[cfg(feature = "bloomfilter")]
+        {
+            let filter = self.url_filter.get_or_init(|| self.build_url_filter());
+            if let Some(filter) = filter {
+                if !filter.check(&url_filter_key(namespace, url)) {
+                    return false;
+                }
+            }
+        }
+
+        matches!(self.get_by_url(namespace, url), Ok(Some(_)))
+    }
+
+    #[cfg(feature = "bloomfilter")]
+    fn build_url_filter(&self) -> Option<Bloom<Vec<u8>>> {
+        let count = self.url_list.len().max(1);
+        let mut filter = Bloom::new_for_fp_rate(count, URL_FILTER_FP_RATE);
+        for entry in self.try_iterate_by_urls() {
+            let entry = entry.ok()?;
+            filter.set(&url_filter_key(entry.namespace, &entry.url));
+        }
+
+        Some(filter)
+    }
+
+    /// Reads all entries into a map from namespace and URL to URL index, which `get_by_url`
+    /// uses instead of a binary search from then on.
+    ///
+    /// This trades memory for faster lookups, so it has to be asked for. Duplicate URLs resolve
+    /// to the entry with the smallest title, as with the binary search. Does nothing if the
+    /// index has already been built.
+    pub fn build_url_index(&self) -> Result<()> {
+        if self.url_index.get().is_some() {
+            return Ok(());
+        }
+
+        let mut index: HashMap<u8, HashMap<String, u32>> = HashMap::new();
+        for (idx, entry) in self.try_iterate_by_urls().enumerate() {
+            let entry = entry?;
+            let urls = index.entry(entry.namespace as u8).or_default();
+            if let Some(&other) = urls.get(&entry.url) {
+                if self.get_by_url_index(other)?.title <= entry.title {
+                    continue;
+                }
+            }
+            urls.insert(entry.url, idx as u32);
+        }
+
+        // another thread may have built it in the meantime, which gives the same result
+        let _ = self.url_index.set(index);
+        Ok(())
+    }
+
+    /// Returns the content of the article at `url` in `namespace`, following redirects.
+    ///
+    /// The returned `DirectoryEntry` is the one which holds the content, so its URL and MIME type
+    /// may differ from the ones requested. Returns `None` if there is no such URL or the final
+    /// entry has no content, and `Error::RedirectLoop` after `MAX_REDIRECTS` redirects.
+    pub fn article_at_url(
+        &self,
+        namespace: Namespace,
+        url: &str,
+    ) -> Result<Option<(DirectoryEntry, Vec<u8>)>> {
+        let entry = match self.get_by_url(namespace, url)? {
+            Some(entry) => self.follow_redirects(entry)?,
+            None => return Ok(None),
+        };
+
+        let mut content = Vec::new();
+        match self.open_article_from_entry(&entry)? {
+            Some(mut reader) => {
+                reader.read_to_end(&mut content)?;
+                Ok(Some((entry, content)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Follows `entry` through redirects until reaching an entry which is not a redirect.
+    ///
+    /// Returns `Error::RedirectLoop` after `MAX_REDIRECTS` redirects.
+    fn follow_redirects(&self, mut entry: DirectoryEntry) -> Result<DirectoryEntry> {
+        let count = self.url_list.len() as u32;
+
+        for _ in 0..MAX_REDIRECTS {
+            match entry.target {
+                Some(Target::Redirect(target)) if target >= count => {
+                    return Err(Error::OutOfBounds)
+                }
+                Some(Target::Redirect(target)) => entry = self.get_by_url_index(target)?,
+                _ => return Ok(entry),
+            }
+        }
+
+        Err(Error::RedirectLoop { url: entry.url })
+    }
+
+    /// Finds redirects which point to another redirect.
+    ///
+    /// Returns `(redirect, target)` URL index pairs, where `target` is the end of the chain, so
+    /// each redirect could point to it directly. Redirect loops and chains longer than
+    /// `MAX_REDIRECTS` are left out, as are entries which fail to parse.
+    pub fn dedup_redirects(&self) -> Vec<(u32, u32)> {
+        let count = self.header.article_count;
+        let redirects: Vec<Option<u32>> = self
+            .try_iterate_by_urls()
+            .map(|entry| match entry.map(|entry| entry.target) {
+                Ok(Some(Target::Redirect(target))) if target < count => Some(target),
+                _ => None,
+            })
+            .collect();
+
+        let mut out = Vec::new();
+        for (idx, target) in redirects.iter().enumerate() {
+            let mut target = match *target {
+                Some(target) if redirects[target as usize].is_some() => target,
+                _ => continue,
+            };
+
+            let mut hops = 1;
+            while let Some(next) = redirects[target as usize] {
+                if hops >= MAX_REDIRECTS {
+                    break;
+                }
+                target = next;
+                hops += 1;
+            }
+
+            if redirects[target as usize].is_none() {
+                out.push((idx as u32, target));
+            }
+        }
+
+        out
+    }
+
+    /// Checks that the URL and title pointer lists can be used for binary search.
+    ///
+    /// URL pointers must be unique and point before the checksum, title pointers must be valid
+    /// URL indices, and the URL pointer list must be sorted by namespace and URL. Returns all
+    /// violations found, or an error if an entry within bounds can't be read.
+    pub fn check_pointer_monotonicity(&self) -> Result<Vec<PointerViolation>> {
+        let mut violations = Vec::new();
+
+        let mut seen = HashMap::with_capacity(self.url_list.len());
+        for (idx, &offset) in self.url_list.iter().enumerate() {
+            let idx = idx as u32;
+            if offset >= self.header.checksum_pos {
+                violations.push(PointerViolation::UrlPointerOutOfBounds { idx, offset });
+            } else if let Some(&first) = seen.get(&offset) {
+                violations.push(PointerViolation::DuplicateUrlPointer { idx, first, offset });
+            } else {
+                seen.insert(offset, idx);
+            }
+        }
+
+        let url_count = self.url_list.len() as u32;
+        for (idx, &url_idx) in self.article_list.iter().enumerate() {
+            if url_idx >= url_count {
+                violations.push(PointerViolation::InvalidTitlePointer {
+                    idx: idx as u32,
+                    url_idx,
+                });
+            }
+        }
+
+        let mut previous: Option<(u32, u8, String)> = None;
+        for (idx, &offset) in self.url_list.iter().enumerate() {
+            let idx = idx as u32;
+            if offset >= self.header.checksum_pos {
+                // already reported, and there is nothing to compare
+                previous = None;
+                continue;
+            }
+
+            let entry = self.get_by_url_index(idx)?;
+            let key = (entry.namespace as u8, entry.url);
+            if let Some((prev_idx, prev_ns, prev_url)) = previous {
+                if (prev_ns, prev_url.as_str()) > (key.0, key.1.as_str()) {
+                    violations.push(PointerViolation::UnsortedUrls {
+                        idx: prev_idx,
+                        next: idx,
+                    });
+                }
+            }
+            previous = Some((idx, key.0, key.1));
+        }
+
+        Ok(violations)
+    }
+
+    /// Checks the structure of the file, beyond what is needed to open it.
+    ///
+    /// Checks that URL pointers point before the checksum and at entries which can be parsed,
+    /// that cluster pointers are increasing and point before the checksum, that redirects and
+    /// the main and layout pages are valid URL indices, and that all MIME types are valid UTF-8.
+    /// All problems found are returned, rather than stopping at the first one. The checksum is
+    /// checked separately by `verify_checksum`.
+    pub fn validate(&self) -> Result<Vec<ValidationWarning>> {
+        let mut warnings = Vec::new();
+        let url_count = self.url_list.len() as u32;
+        let end = self.header.checksum_pos.min(self.master_view.len() as u64);
+
+        for (idx, &offset) in self.url_list.iter().enumerate() {
+            let idx = idx as u32;
+            if offset >= end {
+                warnings.push(ValidationWarning::new(
+                    WarningKind::UrlPointerOutOfBounds,
+                    Some(idx),
+                    format!(
+                        "url pointer {} points to {}, past the end at {}",
+                        idx, offset, end
+                    ),
+                ));
+                continue;
+            }
+
+            match self.get_by_url_index(idx) {
+                Ok(entry) => {
+                    if let Some(Target::Redirect(target)) = entry.target {
+                        if target >= url_count {
+                            warnings.push(ValidationWarning::new(
+                                WarningKind::RedirectOutOfBounds,
+                                Some(idx),
+                                format!(
+                                    "{} redirects to entry {}, but there are only {}",
+                                    entry.full_url(),
+                                    target,
+                                    url_count
+                                ),
+                            ));
+                        }
+                    }
+                }
+                Err(err) => warnings.push(ValidationWarning::new(
+                    WarningKind::InvalidDirectoryEntry,
+                    Some(idx),
+                    format!("entry {} at {} can't be parsed: {}", idx, offset, err),
+                )),
+            }
+        }
+
+        let mut previous: Option<u64> = None;
+        for (idx, &offset) in self.cluster_list.iter().enumerate() {
+            let idx = idx as u32;
+            if offset >= end {
+                warnings.push(ValidationWarning::new(
+                    WarningKind::ClusterPointerOutOfBounds,
+                    Some(idx),
+                    format!(
+                        "cluster {} starts at {}, past the end at {}",
+                        idx, offset, end
+                    ),
+                ));
+            }
+            if let Some(previous) = previous.filter(|&previous| offset <= previous) {
+                warnings.push(ValidationWarning::new(
+                    WarningKind::UnsortedClusterPointer,
+                    Some(idx),
+                    format!(
+                        "cluster {} starts at {}, not after the previous one at {}",
+                        idx, offset, previous
+                    ),
+                ));
+            }
+            previous = Some(offset);
+        }
+
+        for (name, page) in &[
+            ("main", self.header.main_page),
+            ("layout", self.header.layout_page),
+        ] {
+            if let Some(page) = page.filter(|&page| page >= url_count) {
+                warnings.push(ValidationWarning::new(
+                    WarningKind::InvalidPageIndex,
+                    Some(page),
+                    format!(
+                        "{} page is entry {}, but there are only {}",
+                        name, page, url_count
+                    ),
+                ));
+            }
+        }
+
+        // `parse_header` reads the list lossily, so check the bytes in the file
+        let mut cur = Cursor::new(&self.master_view[..]);
+        cur.set_position(self.header.mime_list_pos);
+        for idx in 0.. {
+            let mut mime = Vec::new();
+            if cur.read_until(0, &mut mime)? <= 1 {
+                break;
+            }
+            mime.pop();
+            if std::str::from_utf8(&mime).is_err() {
+                warnings.push(ValidationWarning::new(
+                    WarningKind::InvalidMimeType,
+                    None,
+                    format!("mime type {} is not valid utf-8: {:?}", idx, mime),
+                ));
+            }
+        }
+
+        Ok(warnings)
+    }
+
+    /// Returns a streaming reader over the content of `entry`.
+    ///
+    /// Returns `None` for entries without content, such as redirects.
+    pub fn open_article_from_entry<'a>(
+        &'a self,
+        entry: &DirectoryEntry,
+    ) -> Result<Option<impl Read + 'a>> {
+        match entry.target {
+            Some(Target::Cluster(cluster_idx, blob_idx)) => {
+                Ok(Some(self.open_cluster_stream(cluster_idx, blob_idx)?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Returns the content of `entry`.
+    ///
+    /// Returns `Error::IsRedirect` for redirects and `Error::NoTarget` for entries without
+    /// content. The cluster is only loaded for this call, see `Cluster::into_blob`; to read
+    /// several blobs of a cluster use `get_cluster` instead.
+    pub fn get_blob_for_entry(&self, entry: &DirectoryEntry) -> Result<Blob<'_, '_>> {
+        match entry.target {
+            Some(Target::Cluster(cluster_idx, blob_idx)) => {
+                self.get_cluster(cluster_idx)?.into_blob(blob_idx)
+            }
+            Some(Target::Redirect(_)) => Err(Error::IsRedirect),
+            None => Err(Error::NoTarget),
+        }
+    }
+
+    /// Returns a streaming reader over a single blob.
+    ///
+    /// Blobs of uncompressed clusters are read straight from the file, without copying or
+    /// decompressing anything. Only compressed clusters are decoded, and only up to the end of
+    /// the blob.
+    pub fn open_cluster_stream(&self, cluster_idx: u32, blob_idx: u32) -> Result<impl Read + '_> {
+        self.get_cluster(cluster_idx)?.blob_reader(blob_idx)
+    }
+
+    /// Returns the given `Cluster`
+    ///
+    /// Returns `Error::InvalidClusterIndex` unless idx is between 0 and `cluster_count`, as
+    /// cluster numbers come from directory entries, which may be corrupt.
+    pub fn get_cluster(&self, idx: u32) -> Result<Cluster> {
+        let start = *self
+            .cluster_list
+            .get(idx as usize)
+            .ok_or(Error::InvalidClusterIndex {
+                idx,
+                max: self.cluster_list.len() as u32,
+            })?;
+        // the last cluster ends where the checksum starts
+        let end = match self.cluster_list.get(idx as usize + 1) {
+            Some(&end) => end,
+            None => self.header.checksum_pos,
+        };
+
+        match self.pool {
+            Some(ref pool) => Cluster::new_with_pool(
+                &self.master_view,
+                idx,
+                start,
+                end,
+                self.header.version_major,
+                pool.clone(),
+            ),
+            None => Cluster::new(
+                &self.master_view,
+                idx,
+                start,
+                end,
+                self.header.version_major,
+            ),
+        }
+    }
+
+    /// Iterates over all clusters, by index, together with their index.
+    ///
+    /// Each cluster is loaded once, so processing all blobs of a cluster before moving on to the
+    /// next decompresses less than going through the entries with `iterate_by_urls`.
+    pub fn iterate_clusters(
+        &self,
+    ) -> impl ExactSizeIterator<Item = (u32, Result<Cluster<'_>>)> + '_ {
+        (0..self.header.cluster_count).map(move |idx| (idx, self.get_cluster(idx)))
+    }
+}
+
+impl Zim {
+    /// Loads and decompresses all clusters in parallel, on rayon's thread pool.
+    ///
+    /// Returns the clusters by index, together with the index and error of every cluster which
+    /// failed to load or decompress, sorted by index. Clusters which failed to decompress are
+    /// still returned, so reading their blobs reports the error again. Note that all
+    /// decompressed data is kept in memory.
+    pub fn decompress_all_clusters(&self) -> (HashMap<u32, Cluster<'_>>, Vec<(u32, Error)>) {
+        let all: Vec<u32> = (0..self.header.cluster_count).collect();
+        self.decompress_clusters(&all)
+    }
+
+    /// Like `decompress_all_clusters`, but only for the clusters at `indices`, e.g. those holding
+    /// the entries to extract.
+    pub fn decompress_clusters(
+        &self,
+        indices: &[u32],
+    ) -> (HashMap<u32, Cluster<'_>>, Vec<(u32, Error)>) {
+        let loaded: Vec<(u32, Result<Cluster>)> = indices
+            .par_iter()
+            .map(|&idx| (idx, self.get_cluster(idx)))
+            .collect();
+
+        let mut clusters = HashMap::with_capacity(loaded.len());
+        let mut failures = Vec::new();
+        for (idx, cluster) in loaded {
+            match cluster {
+                Ok(cluster) => {
+                    clusters.insert(idx, cluster);
+                }
+                Err(err) => failures.push((idx, err)),
+            }
+        }
+
+        failures.par_extend(
+            clusters
+                .par_iter()
+                .filter_map(|(idx, cluster)| cluster.decompress().err().map(|err| (*idx, err))),
+        );
+        failures.sort_by_key(|(idx, _)| *idx);
+
+        (clusters, failures)
+    }
+}
+
+/// The raw namespace, URL and title of a directory entry, see `Zim::entry_key`.
+struct EntryKey<'a> {
+    namespace: u8,
+    url: &'a [u8],
+    title: &'a [u8],
+}
+
+impl<'a> EntryKey<'a> {
+    /// Like `entry_title`.
+    fn sort_title(&self) -> &'a [u8] {
+        if self.title.is_empty() {
+            self.url
+        } else {
+            self.title
+        }
+    }
+}
+
+/// The title used for sorting: the URL is used when the title is empty.
+fn entry_title(entry: &DirectoryEntry) -> &str {
+    if entry.title.is_empty() {
+        &entry.url
+    } else {
+        &entry.title
+    }
+}
+
+/// Returns the first index in `0..count` for which `is_less` is false.
+///
+/// `is_less` must be true for a (possibly empty) prefix of the range and false afterwards.
+fn lower_bound<F>(count: u32, is_less: F) -> Result<u32>
+where
+    F: Fn(u32) -> Result<bool>,
+{
+    let mut low = 0;
+    let mut high = count;
+    while low < high {
+        let mid = low + (high - low) / 2;
+        if is_less(mid)? {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+
+    Ok(low)
+}
+
+fn is_defined(val: u32) -> Option<u32> {
+    if val == 0xffffffff {
+        None
+    } else {
+        Some(val)
+    }
+}
+
+fn parse_header(master_view: &[u8]) -> Result<(ZimHeader, Vec<String>)> {
+    let mut header_cur = Cursor::new(master_view);
+
+    let magic = header_cur.read_u32::<LittleEndian>()?;
+
+    if magic != ZIM_MAGIC_NUMBER {
+        return Err(Error::InvalidMagicNumber {
+            found: magic,
+            expected: ZIM_MAGIC_NUMBER,
+        });
+    }
+
+    let version_major = header_cur.read_u16::<LittleEndian>()?;
+    if version_major != 5 && version_major != 6 {
+        return Err(Error::InvalidVersion);
+    }
+
+    let version_minor = header_cur.read_u16::<LittleEndian>()?;
+
+    let mut uuid = [0u8; 16];
+    for i in 0..16 {
+        uuid[i] = header_cur.read_u8()?;
+    }
+
+    let article_count = header_cur.read_u32::<LittleEndian>()?;
+    let cluster_count = header_cur.read_u32::<LittleEndian>()?;
+    let url_ptr_pos = header_cur.read_u64::<LittleEndian>()?;
+    let title_ptr_pos = header_cur.read_u64::<LittleEndian>()?;
+    let cluster_ptr_pos = header_cur.read_u64::<LittleEndian>()?;
+    let mime_list_pos = header_cur.read_u64::<LittleEndian>()?;
+
+    let main_page = header_cur.read_u32::<LittleEndian>()?;
+    let layout_page = header_cur.read_u32::<LittleEndian>()?;
+    let checksum_pos = header_cur.read_u64::<LittleEndian>()?;
+
+    if header_cur.position() != 80 {
+        return Err(Error::InvalidHeader);
+    }
+
+    let checksum_type =
+        ChecksumType::from_len((master_view.len() as u64).saturating_sub(checksum_pos));
+
+    let geo_index_pos = if mime_list_pos > 80 {
+        Some(header_cur.read_u64::<LittleEndian>()?)
+    } else {
+        None
+    };
+
+    // the mime table is always directly after the 80-byte header, so we'll keep
+    // using our header cursor
+    let mime_table = {
+        let mut mime_table = Vec::new();
+        loop {
+            let mut mime_buf = Vec::new();
+            if let Ok(size) = header_cur.read_until(0, &mut mime_buf) {
+                if size <= 1 {
+                    break;
+                }
+                mime_buf.truncate(size - 1);
+                // invalid MIME types only affect their entries, they are reported by `validate`
+                mime_table.push(String::from_utf8_lossy(&mime_buf).into_owned());
+            }
+        }
+        mime_table
+    };
+
+    // the checksum comes after everything else, in particular after the mime table
+    if checksum_pos < header_cur.position() {
+        return Err(Error::InvalidHeaderPointer {
+            field: "checksum_pos",
+        });
+    }
+
+    Ok((
+        ZimHeader {
+            version_major,
+            version_minor,
+            uuid: Uuid::new(uuid),
+            article_count,
+            cluster_count,
+            url_ptr_pos,
+            title_ptr_pos,
+            cluster_ptr_pos,
+            mime_list_pos,
+            main_page: is_defined(main_page),
+            layout_page: is_defined(layout_page),
+            checksum_pos,
+            checksum_type,
+            geo_index_pos,
+        },
+        mime_table,
+    ))
+}
+
+/// Maps `file`, falling back to reading it into memory if the file system does not support
+/// memory mapping.
+#[cfg(not(target_arch = "wasm32"))]
+fn map_file<M>(file: File, map: M) -> Result<MasterView>
+where
+    M: FnOnce(&File) -> io::Result<Mmap>,
+{
+    match map(&file) {
+        Ok(map) => Ok(MasterView::Mmap(map)),
+        Err(ref err) if is_mmap_unsupported(err) => read_file(file),
+        Err(err) => Err(err.into()),
+    }
+}
+
+/// Reads all of `file` into memory.
+fn read_file(mut file: File) -> Result<MasterView> {
+    let mut data = Vec::new();
+    file.read_to_end(&mut data)?;
+    Ok(MasterView::Bytes(data.into()))
+}
+
+/// The namespace character followed by the URL, as stored in the URL filter.
+#[cfg(feature = "bloomfilter")]
+fn url_filter_key(namespace: Namespace, url: &str) -> Vec<u8> {
+    let mut key = Vec::with_capacity(1 + url.len());
+    key.push(namespace as u8);
+    key.extend_from_slice(url.as_bytes());
+    key
+}
+
+#[cfg(unix)]
+fn is_mmap_unsupported(err: &io::Error) -> bool {
+    match err.raw_os_error() {
+        Some(code) => code == libc::ENOTSUP || code == libc::EOPNOTSUPP || code == libc::ENODEV,
+        None => false,
+    }
+}
+
+#[cfg(all(not(unix), not(target_arch = "wasm32")))]
+fn is_mmap_unsupported(err: &io::Error) -> bool {
+    err.kind() == io::ErrorKind::Unsupported
+}
+
+/// Returns `master_view[start..end]`, or `Error::TruncatedFile` if the file ends before `end`.
+pub(crate) fn file_region(master_view: &[u8], start: u64, end: u64) -> Result<&[u8]> {
+    let actual_size = master_view.len() as u64;
+    if end > actual_size {
+        return Err(Error::TruncatedFile {
+            expected_end: end,
+            actual_size,
+        });
+    }
+
+    master_view
+        .get(start as usize..end as usize)
+        .ok_or(Error::OutOfBounds)
+}
+
+/// Parses the URL Pointer List.
+/// See https://wiki.openzim.org/wiki/ZIM_file_format#URL_Pointer_List_.28urlPtrPos.29
+fn parse_url_list(master_view: &[u8], ptr_pos: u64, count: u32) -> Result<Vec<u64>> {
+    let end = ptr_pos
+        .checked_add(count as u64 * 8)
+        .ok_or(Error::OutOfBounds)?;
+    let list_view = file_region(master_view, ptr_pos, end)?;
+    let mut cur = Cursor::new(list_view);
+
+    let mut out: Vec<u64> = Vec::new();
+    for _ in 0..count {
+        out.push(cur.read_u64::<LittleEndian>()?);
+    }
+
+    Ok(out)
+}
+
+fn parse_article_list(master_view: &[u8], ptr_pos: u64, count: u32) -> Result<Vec<u32>> {
+    let end = ptr_pos
+        .checked_add(count as u64 * 4)
+        .ok_or(Error::OutOfBounds)?;
+    let list_view = file_region(master_view, ptr_pos, end)?;
+
+    let mut cur = Cursor::new(list_view);
+    let mut out: Vec<u32> = Vec::new();
+
+    for _ in 0..count {
+        out.push(cur.read_u32::<LittleEndian>()?);
+    }
+
+    Ok(out)
+}
+
+fn parse_cluster_list(master_view: &[u8], ptr_pos: u64, count: u32) -> Result<Vec<u64>> {
+    let end = ptr_pos
+        .checked_add(count as u64 * 8)
+        .ok_or(Error::OutOfBounds)?;
+    let cluster_list_view = file_region(master_view, ptr_pos, end)?;
+
+    let mut cluster_cur = Cursor::new(cluster_list_view);
+    let mut out: Vec<u64> = Vec::new();
+    for _ in 0..count {
+        out.push(cluster_cur.read_u64::<LittleEndian>()?);
+    }
+    Ok(out)
+}
+
+/// Adds a `text/html` article for each `(namespace, url, title)`, which has its URL as content,
+/// and a redirect for each `(namespace, url, target_url)` to the target in the same namespace.
+#[cfg(test)]
+pub(crate) fn writer_with_articles(
+    articles: &[(Namespace, &str, &str)],
+    redirects: &[(Namespace, &str, &str)],
+) -> crate::writer::ZimWriter {
+    let mut writer = crate::writer::ZimWriter::new();
+    for &(namespace, url, title) in articles {
+        writer
+            .add_article(namespace, url, title, "text/html", url.as_bytes())
+            .unwrap();
+    }
+    for &(namespace, url, target_url) in redirects {
+        writer
+            .add_redirect(namespace, url, "", target_url, namespace)
+            .unwrap();
+    }
+    writer
+}
+
+/// Writes the file of `writer` and opens it from memory.
+#[cfg(test)]
+pub(crate) fn open_written(writer: crate::writer::ZimWriter) -> Zim {
+    let mut raw = Vec::new();
+    writer.finalize(&mut raw).unwrap();
+    Zim::new_from_reader(Cursor::new(raw)).unwrap()
+}
+
+/// Writes a file with the articles of `writer_with_articles`.
+#[cfg(test)]
+pub(crate) fn write_articles(articles: &[(Namespace, &str, &str)]) -> Vec<u8> {
+    let mut raw = Vec::new();
+    writer_with_articles(articles, &[])
+        .finalize(&mut raw)
+        .unwrap();
+    raw
+}
+
+/// Opens a file with the articles of `writer_with_articles` from memory.
+#[cfg(test)]
+pub(crate) fn zim_with_articles(articles: &[(Namespace, &str, &str)]) -> Zim {
+    open_written(writer_with_articles(articles, &[]))
+}
+
+/// Returns a path in the temporary directory which is not used by any other test, including
+/// those of concurrent runs.
+#[cfg(test)]
+pub(crate) fn temp_path(name: &str) -> PathBuf {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+    std::env::temp_dir().join(format!("zim_test_{}_{}_{}", name, std::process::id(), n))
+}
+
+/// Opens a file with "Apple", "Banana" and "Cherry", with an invalid UTF-8 sequence in the URL of
+/// "Banana".
+#[cfg(test)]
+pub(crate) fn zim_with_broken_entry() -> Zim {
+    let mut raw = write_articles(&[
+        (Namespace::Articles, "Apple", ""),
+        (Namespace::Articles, "Banana", ""),
+        (Namespace::Articles, "Cherry", ""),
+    ]);
+    let pos = raw.windows(7).position(|w| w == b"Banana\0").unwrap();
+    raw[pos] = 0xff;
+    Zim::new_from_reader(Cursor::new(raw)).unwrap()
+}
+
+#[test]
+fn test_zim() {
+    let zim = Zim::new("fixtures/wikipedia_ab_all_2017-03.zim").expect("failed to parse fixture");
+
+    assert_eq!(zim.header.version_major, 5);
+    let cl0 = zim.get_cluster(0).unwrap();
+    assert_eq!(&cl0.get_blob(0).unwrap()[..], &[97, 98, 107][..]);
+
+    let cl1 = zim.get_cluster(zim.header.cluster_count - 1).unwrap();
+    let b = cl1.get_blob(0).unwrap();
+    assert_eq!(&b[0..10], &[71, 73, 70, 56, 57, 97, 44, 1, 150, 0]);
+    assert_eq!(
+        &b[b.len() - 10..],
+        &[222, 192, 21, 240, 155, 91, 65, 0, 0, 59]
+    );
+
+    assert_eq!(zim.iterate_by_urls().count(), 3111);
+}
+
+#[test]
+fn test_try_iterate_by_urls() {
+    let zim = zim_with_broken_entry();
+
+    let entries: Vec<_> = zim.try_iterate_by_urls().collect();
+    assert_eq!(entries.len(), 3);
+    assert_eq!(entries[0].as_ref().unwrap().url, "Apple");
+    assert!(matches!(entries[1], Err(Error::Utf8(_))));
+    assert_eq!(entries[2].as_ref().unwrap().url, "Cherry");
+}
+
+#[test]
+fn test_partition_url_ranges() {
+    let zim = zim_with_articles(&[
+        (Namespace::Articles, "Apple", ""),
+        (Namespace::Articles, "Banana", ""),
+        (Namespace::Articles, "Cherry", ""),
+        (Namespace::Articles, "Date", ""),
+        (Namespace::Articles, "Elderberry", ""),
+    ]);
+
+    assert_eq!(zim.partition_url_ranges(2), [0..3, 3..5]);
+    assert_eq!(zim.partition_url_ranges(7).len(), 7);
+    assert_eq!(zim.partition_url_ranges(7)[6], 5..5);
+    assert!(zim.partition_url_ranges(0).is_empty());
+
+    let urls = |start, end| -> Vec<String> {
+        zim.iter_articles_in_url_range(start, end)
+            .map(|entry| entry.unwrap().url)
+            .collect()
+    };
+    assert_eq!(urls(1, 3), ["Banana", "Cherry"]);
+    // the end is clamped to the article count
+    assert_eq!(urls(3, 10), ["Date", "Elderberry"]);
+    assert!(urls(4, 2).is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_advise() {
+    let path = temp_path("advise");
+    let mut raw = Vec::new();
+    crate::writer::ZimWriter::new().finalize(&mut raw).unwrap();
+    std::fs::write(&path, &raw).unwrap();
+
+    let mapped = Zim::new(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(mapped.master_view, MasterView::Mmap(_)));
+    mapped.advise_sequential().unwrap();
+    mapped.advise_random().unwrap();
+
+    let in_memory = Zim::open_from_bytes(raw.clone().into()).unwrap();
+    in_memory.advise_sequential().unwrap();
+    in_memory.advise_random().unwrap();
+
+    std::fs::write(&path, &raw).unwrap();
+    let advised = ZimOpenOptions::new()
+        .mmap_advice(Advice::Sequential)
+        .open(&path);
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(advised.unwrap().master_view, MasterView::Mmap(_)));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_zim_mmap_unsupported() {
+    let path = temp_path("mmap_unsupported");
+    std::fs::write(&path, b"not really a zim file").unwrap();
+
+    let unsupported = || Err(io::Error::from_raw_os_error(libc::ENOTSUP));
+    let view = map_file(File::open(&path).unwrap(), |_| unsupported()).unwrap();
+    match view {
+        MasterView::Bytes(ref data) => assert_eq!(&data[..], b"not really a zim file"),
+        MasterView::Mmap(_) => panic!("expected the file to be read into memory"),
+    }
+
+    // other errors are passed on
+    let denied = || Err(io::Error::from_raw_os_error(libc::EACCES));
+    let result = map_file(File::open(&path).unwrap(), |_| denied());
+    std::fs::remove_file(&path).unwrap();
+    assert!(matches!(result, Err(Error::Io(_))));
+}
+
+#[test]
+fn test_article_at_url() {
+    let zim = open_written(writer_with_articles(
+        &[(Namespace::Articles, "Page", "")],
+        &[
+            (Namespace::Articles, "A", "B"),
+            (Namespace::Articles, "B", "C"),
+            (Namespace::Articles, "C", "Page"),
+            (Namespace::Articles, "Loop", "Loop"),
+        ],
+    ));
+
+    let (entry, content) = zim
+        .article_at_url(Namespace::Articles, "A")
+        .unwrap()
+        .unwrap();
+    assert_eq!(entry.url, "Page");
+    assert_eq!(entry.mime_type, MimeType::Type("text/html".into()));
+    assert_eq!(content, b"Page");
+
+    assert!(zim
+        .article_at_url(Namespace::Articles, "Missing")
+        .unwrap()
+        .is_none());
+    assert!(zim
+        .article_at_url(Namespace::ImagesFile, "Page")
+        .unwrap()
+        .is_none());
+    assert!(matches!(
+        zim.article_at_url(Namespace::Articles, "Loop"),
+        Err(Error::RedirectLoop { .. })
+    ));
+
+    // URL order: A, B, C, Loop, Page
+    assert_eq!(zim.dedup_redirects(), vec![(0, 4), (1, 4)]);
+}
+
+#[test]
+fn test_articles_by_namespace_then_title() {
+    let zim = zim_with_articles(&[
+        (Namespace::Metadata, "Title", ""),
+        (Namespace::Articles, "b", "Zebra"),
+        (Namespace::Layout, "style.css", ""),
+        (Namespace::Articles, "z", "Apple"),
+        (Namespace::ImagesFile, "logo.png", ""),
+    ]);
+
+    let order: Vec<String> = zim
+        .articles_by_namespace_then_title()
+        .map(|entry| entry.map(|entry| entry.url))
+        .collect::<Result<_>>()
+        .unwrap();
+    assert_eq!(order, vec!["style.css", "z", "b", "logo.png", "Title"]);
+}
+
+#[test]
+fn test_count_by_namespace() {
+    let zim = zim_with_articles(&[
+        (Namespace::Articles, "Apple", ""),
+        (Namespace::Articles, "Banana", ""),
+        (Namespace::Articles, "Cherry", ""),
+        (Namespace::Layout, "style.css", ""),
+        (Namespace::Metadata, "Title", ""),
+    ]);
+
+    assert_eq!(zim.count_by_namespace(Namespace::Articles).unwrap(), 3);
+    assert_eq!(zim.count_by_namespace(Namespace::Layout).unwrap(), 1);
+    assert_eq!(zim.count_by_namespace(Namespace::Metadata).unwrap(), 1);
+    assert_eq!(zim.count_by_namespace(Namespace::ImagesFile).unwrap(), 0);
+    assert_eq!(zim.count_by_namespace(Namespace::FulltextIndex).unwrap(), 0);
+    assert!(!zim.has_full_text_index());
+    assert!(!zim.has_geo_index());
+    assert!(zim.geo_index().is_none());
+
+    let zim = zim_with_articles(&[(Namespace::FulltextIndex, "title", "")]);
+    assert!(zim.has_full_text_index());
+}
+
+#[test]
+fn test_checksum_pos_before_mime_table() {
+    let mut raw = write_articles(&[(Namespace::Articles, "Apple", "")]);
+
+    // checksum_pos is the last header field, at offset 72
+    raw[72..80].copy_from_slice(&82u64.to_le_bytes());
+    match Zim::new_from_reader(Cursor::new(raw)) {
+        Err(Error::InvalidHeaderPointer { field }) => assert_eq!(field, "checksum_pos"),
+        _ => panic!("expected an invalid header pointer error"),
+    }
+}
+
+#[test]
+fn test_mime_types() {
+    let mut writer = writer_with_articles(&[(Namespace::Articles, "Apple", "")], &[]);
+    for url in &["a.png", "b.png"] {
+        writer
+            .add_article(Namespace::ImagesFile, url, "", "image/png", b"")
+            .unwrap();
+    }
+    let zim = open_written(writer);
+
+    assert_eq!(
+        zim.mime_types().collect::<Vec<_>>(),
+        vec!["text/html", "image/png"]
+    );
+    assert_eq!(zim.mime_type_count(), 2);
+    assert!(zim.has_mime_type("image/png"));
+    assert!(!zim.has_mime_type("image/jpeg"));
+}
+
+#[test]
+fn test_get_by_url_duplicate() {
+    let mut raw = write_articles(&[
+        (Namespace::Articles, "Apple", "Zed"),
+        (Namespace::Articles, "Apply", "Abc"),
+    ]);
+
+    // corrupt the file, so both entries have the same URL
+    let pos = raw.windows(6).position(|w| w == b"Apply\0").unwrap();
+    raw[pos + 4] = b'e';
+    let zim = Zim::new_from_reader(Cursor::new(raw)).unwrap();
+
+    // the same result with binary search and with the URL index
+    for _ in 0..2 {
+        let entry = zim
+            .get_by_url(Namespace::Articles, "Apple")
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.title, "Abc");
+        assert!(zim
+            .get_by_url(Namespace::Articles, "Apply")
+            .unwrap()
+            .is_none());
+        assert!(zim
+            .get_by_url(Namespace::Layout, "Apple")
+            .unwrap()
+            .is_none());
+
+        zim.build_url_index().unwrap();
+    }
+    assert!(zim.url_index.get().is_some());
+}
+
+#[test]
+fn test_zim_reopen() {
+    let raw = write_articles(&[(Namespace::Articles, "Apple", "")]);
+    let path = temp_path("reopen");
+    std::fs::write(&path, &raw).unwrap();
+
+    // whether the process still maps the file or holds it open
+    #[cfg(target_os = "linux")]
+    let in_use = |path: &Path| {
+        let path = std::fs::canonicalize(path).unwrap();
+        let maps = std::fs::read_to_string("/proc/self/maps").unwrap();
+        let mut fds = std::fs::read_dir("/proc/self/fd").unwrap();
+        maps.contains(path.to_str().unwrap())
+            || fds.any(|fd| std::fs::read_link(fd.unwrap().path()).is_ok_and(|fd| fd == path))
+    };
+
+    {
+        let zim = Zim::new(&path).unwrap();
+        assert!(matches!(zim.master_view, MasterView::Mmap(_)));
+        let cluster = zim.get_cluster(0).unwrap();
+        assert_eq!(&cluster.get_blob(0).unwrap()[..], b"Apple");
+        #[cfg(target_os = "linux")]
+        assert!(in_use(&path));
+    }
+    #[cfg(target_os = "linux")]
+    assert!(!in_use(&path));
+
+    // without a map or handle left, the file can be overwritten and moved right away, which
+    // fails on Windows while it is mapped
+    std::fs::write(&path, &raw).unwrap();
+    let moved = temp_path("reopen_moved");
+    std::fs::rename(&path, &moved).unwrap();
+    let zim = Zim::new(&moved).unwrap();
+    assert_eq!(zim.header.article_count, 1);
+    drop(zim);
+    std::fs::remove_file(&moved).unwrap();
+}
+
+#[test]
+fn test_zim_par_iter() {
+    use rayon::prelude::*;
+    use std::collections::HashSet;
+
+    let zim = Zim::new("fixtures/wikipedia_ab_all_2017-03.zim").expect("failed to parse fixture");
+
+    let sequential: HashSet<String> = zim.iterate_by_urls().map(|entry| entry.url).collect();
+    let parallel: HashSet<String> = zim.par_iter().map(|entry| entry.unwrap().url).collect();
+
+    assert_eq!(sequential, parallel);
+}
+
+#[test]
+fn test_open_cluster_stream() {
+    let zim = zim_with_articles(&[
+        (Namespace::Articles, "Apple", ""),
+        (Namespace::Articles, "Banana", ""),
+    ]);
+
+    let entry = zim
+        .get_by_url(Namespace::Articles, "Banana")
+        .unwrap()
+        .unwrap();
+    let (cluster_idx, blob_idx) = match entry.target {
+        Some(Target::Cluster(cluster_idx, blob_idx)) => (cluster_idx, blob_idx),
+        _ => panic!("expected an article"),
+    };
+
+    let mut data = Vec::new();
+    zim.open_cluster_stream(cluster_idx, blob_idx)
+        .unwrap()
+        .read_to_end(&mut data)
+        .unwrap();
+    assert_eq!(data, b"Banana");
+}
+
+#[test]
+fn test_invalid_magic_number() {
+    let mut raw = Vec::new();
+    crate::writer::ZimWriter::new().finalize(&mut raw).unwrap();
+    raw[..4].copy_from_slice(b"PK\x03\x04");
+
+    let err = match Zim::new_from_reader(Cursor::new(raw)) {
+        Ok(_) => panic!("expected an error"),
+        Err(err) => err,
+    };
+    match err {
+        Error::InvalidMagicNumber { found, expected } => {
+            assert_eq!(found, 0x0403_4B50);
+            assert_eq!(expected, ZIM_MAGIC_NUMBER);
+        }
+        _ => panic!("expected InvalidMagicNumber, got {:?}", err),
+    }
+    assert_eq!(
+        err.to_string(),
+        "invalid magic number: found 0x04034B50, expected 0x044D495A"
+    );
+}
+
+#[cfg(feature = "rand")]
+#[test]
+fn test_get_random_entry() {
+    let zim = open_written(writer_with_articles(
+        &[
+            (Namespace::Articles, "Apple", ""),
+            (Namespace::Metadata, "Title", ""),
+        ],
+        &[(Namespace::Articles, "Fruit", "Apple")],
+    ));
+
+    for _ in 0..8 {
+        // the redirect is followed, so this always ends up at the article
+        assert_eq!(zim.get_random_article().unwrap().url, "Apple");
+    }
+    assert_eq!(
+        zim.get_random_entry(Namespace::Metadata).unwrap().url,
+        "Title"
+    );
+    match zim.get_random_entry(Namespace::ImagesFile) {
+        Err(Error::EmptyNamespace) => {}
+        other => panic!("expected EmptyNamespace, got {:?}", other.map(|e| e.url)),
+    }
+}
+
+#[test]
+fn test_open_from_bytes() {
+    let data: Arc<[u8]> = write_articles(&[(Namespace::Articles, "Apple", "")]).into();
+    let zim = Zim::open_from_bytes(data.clone()).unwrap();
+    // the data is shared with the caller rather than copied
+    assert_eq!(zim.master_view.as_ptr(), data.as_ptr());
+    zim.verify_checksum().unwrap();
+
+    let (_, content) = zim
+        .article_at_url(Namespace::Articles, "Apple")
+        .unwrap()
+        .unwrap();
+    assert_eq!(content, b"Apple");
+}
+
+#[test]
+fn test_contains_url() {
+    let zim = open_written(writer_with_articles(
+        &[
+            (Namespace::Articles, "Apple", ""),
+            (Namespace::Metadata, "Title", ""),
+        ],
+        &[(Namespace::Articles, "Fruit", "Apple")],
+    ));
+
+    assert!(zim.contains_url("Apple", Namespace::Articles));
+    assert!(zim.contains_url("Fruit", Namespace::Articles));
+    assert!(zim.contains_url("Title", Namespace::Metadata));
+    assert!(!zim.contains_url("Title", Namespace::Articles));
+    assert!(!zim.contains_url("Banana", Namespace::Articles));
+}
+
+#[test]
+fn test_check_pointer_monotonicity() {
+    let mut zim = zim_with_articles(&[
+        (Namespace::Articles, "Apple", ""),
+        (Namespace::Articles, "Banana", ""),
+        (Namespace::Articles, "Cherry", ""),
+    ]);
+    assert_eq!(zim.check_pointer_monotonicity().unwrap(), vec![]);
+
+    zim.url_list.swap(0, 1);
+    zim.url_list[2] = zim.url_list[0];
+    zim.article_list[1] = 7;
+    assert_eq!(
+        zim.check_pointer_monotonicity().unwrap(),
+        vec![
+            PointerViolation::DuplicateUrlPointer {
+                idx: 2,
+                first: 0,
+                offset: zim.url_list[0],
+            },
+            PointerViolation::InvalidTitlePointer { idx: 1, url_idx: 7 },
+            PointerViolation::UnsortedUrls { idx: 0, next: 1 },
+        ]
+    );
+
+    zim.url_list[2] = zim.header.checksum_pos;
+    assert_eq!(
+        zim.check_pointer_monotonicity().unwrap(),
+        vec![
+            PointerViolation::UrlPointerOutOfBounds {
+                idx: 2,
+                offset: zim.header.checksum_pos,
+            },
+            PointerViolation::InvalidTitlePointer { idx: 1, url_idx: 7 },
+            PointerViolation::UnsortedUrls { idx: 0, next: 1 },
+        ]
+    );
+}
+
+#[test]
+fn test_validate() {
+    let mut raw = Vec::new();
+    writer_with_articles(
+        &[(Namespace::Articles, "Apple", "")],
+        &[(Namespace::Articles, "Fruit", "Apple")],
+    )
+    .finalize(&mut raw)
+    .unwrap();
+    let zim = Zim::new_from_reader(Cursor::new(raw.clone())).unwrap();
+    assert_eq!(zim.validate().unwrap(), vec![]);
+
+    // point the redirect past the end, and break the first MIME type
+    let redirect = zim.url_list[1] as usize;
+    raw[redirect + 8..redirect + 12].copy_from_slice(&9u32.to_le_bytes());
+    raw[zim.header.mime_list_pos as usize] = 0xff;
+    let mut zim = Zim::new_from_reader(Cursor::new(raw)).unwrap();
+    // the magic number is not a valid entry
+    zim.url_list.push(0);
+    zim.url_list.push(zim.header.checksum_pos);
+    zim.cluster_list.push(zim.cluster_list[0]);
+    zim.header.main_page = Some(5);
+
+    let warnings: Vec<_> = zim
+        .validate()
+        .unwrap()
+        .into_iter()
+        .map(|warning| (warning.kind, warning.cluster_or_entry_idx))
+        .collect();
+    assert_eq!(
+        warnings,
+        vec![
+            (WarningKind::RedirectOutOfBounds, Some(1)),
+            (WarningKind::InvalidDirectoryEntry, Some(2)),
+            (WarningKind::UrlPointerOutOfBounds, Some(3)),
+            (WarningKind::UnsortedClusterPointer, Some(1)),
+            (WarningKind::InvalidPageIndex, Some(5)),
+            (WarningKind::InvalidMimeType, None),
+        ]
+    );
+}
+
+#[test]
+fn test_title_prefix_matches() {
+    let zim = zim_with_articles(&[
+        (Namespace::Articles, "Apple", ""),
+        (Namespace::Articles, "Apricot", ""),
+        (Namespace::Articles, "Banana", "apple banana"),
+        (Namespace::ImagesFile, "apple.png", "Apple image"),
+    ]);
+
+    let urls = |namespace, case_insensitive| -> Vec<String> {
+        zim.title_prefix_matches("Ap", namespace, case_insensitive)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.full_url())
+            .collect()
+    };
+    assert_eq!(
+        urls(Some(Namespace::Articles), false),
+        ["A/Apple", "A/Apricot"]
+    );
+    assert_eq!(urls(None, false), ["A/Apple", "A/Apricot", "I/apple.png"]);
+    assert_eq!(
+        urls(None, true),
+        ["A/Apple", "A/Apricot", "A/Banana", "I/apple.png"]
+    );
+}
+
+#[test]
+fn test_lossy_utf8() {
+    let mut raw = write_articles(&[(Namespace::Articles, "Apple", "Apple")]);
+
+    // turn the first byte of the URL into an invalid UTF-8 sequence
+    let pos = raw.windows(6).position(|w| w == b"Apple\0").unwrap();
+    raw[pos] = 0xff;
+
+    let zim = Zim::new_from_reader(Cursor::new(raw)).unwrap();
+    match zim.get_by_url_index(0) {
+        Err(Error::Utf8(_)) => {}
+        _ => panic!("expected a UTF-8 error"),
+    }
+    assert_eq!(zim.iterate_by_urls().count(), 0);
+
+    let zim = zim.with_lossy_utf8();
+    let entry = zim.get_by_url_index(0).unwrap();
+    assert_eq!(entry.url, "\u{fffd}pple");
+    assert_eq!(entry.title, "Apple");
+    assert_eq!(zim.iterate_by_urls().count(), 1);
+}
+
+#[test]
+fn test_articles() {
+    let open = |namespaces: &[Namespace]| {
+        let articles: Vec<_> = namespaces.iter().map(|&ns| (ns, "Apple", "")).collect();
+        zim_with_articles(&articles)
+    };
+    let urls = |zim: &Zim| -> Vec<String> {
+        zim.articles()
+            .unwrap()
+            .map(|entry| entry.unwrap().full_url())
+            .collect()
+    };
+
+    let legacy = open(&[Namespace::Articles, Namespace::ImagesFile]);
+    assert!(!legacy.has_unified_namespace());
+    assert_eq!(urls(&legacy), ["A/Apple"]);
+
+    let unified = open(&[Namespace::Content, Namespace::Metadata]);
+    assert!(unified.has_unified_namespace());
+    assert_eq!(urls(&unified), ["C/Apple"]);
+}
+
+#[test]
+fn test_header_display() {
+    let mut writer = writer_with_articles(&[(Namespace::Articles, "Apple", "")], &[]);
+    writer.set_main_page(Namespace::Articles, "Apple");
+    let zim = open_written(writer);
+
+    let header = zim.clone_header();
+    drop(zim);
+    let summary = header.to_string();
+    assert!(summary.contains(&format!("UUID: {}", header.uuid)));
+    assert!(summary.contains("Article Count: 1\n"));
+    assert!(summary.contains("Main Page Index: 0\n"));
+    assert!(summary.contains("Layout Page Index: -\n"));
+    assert!(summary.ends_with(&format!("Checksum Pos: {}", header.checksum_pos)));
+}
+
+#[test]
+fn test_iterate_clusters() {
+    use crate::writer::ZimWriterBuilder;
+
+    let mut writer = ZimWriterBuilder::new().cluster_size(4).build();
+    for url in &["Apple", "Banana", "Cherry"] {
+        writer
+            .add_article(Namespace::Articles, url, "", "text/html", url.as_bytes())
+            .unwrap();
+    }
+    let zim = open_written(writer);
+
+    let clusters = zim.iterate_clusters();
+    assert_eq!(clusters.len(), zim.header.cluster_count as usize);
+    let indices: Vec<u32> = clusters
+        .map(|(idx, cluster)| {
+            assert_eq!(cluster.unwrap().blob_count().unwrap(), 1);
+            idx
+        })
+        .collect();
+    assert_eq!(indices, [0, 1, 2]);
+}
+
+#[test]
+fn test_pointer_lists_beyond_4gib() {
+    let view = [0u8; 64];
+    let ptr_pos = (1u64 << 32) - 4;
+
+    // the end of the lists must not wrap around at 4 GiB
+    for result in &[
+        parse_article_list(&view, ptr_pos, 2).map(|_| ()),
+        parse_cluster_list(&view, ptr_pos, 2).map(|_| ()),
+        parse_url_list(&view, ptr_pos, 2).map(|_| ()),
+    ] {
+        match result {
+            Err(Error::TruncatedFile { expected_end, .. }) => assert!(*expected_end > ptr_pos),
+            _ => panic!("expected a truncated file error"),
+        }
+    }
+
+    match parse_cluster_list(&view, u64::MAX - 4, 1) {
+        Err(Error::OutOfBounds) => {}
+        _ => panic!("expected an out of bounds error"),
+    }
+}
+
+#[test]
+fn test_iterate_last_entry() {
+    // with long titles, the directory entries make up most of the file, so the last ones start
+    // past its middle
+    let urls: Vec<String> = (0..20).map(|i| format!("Article_{:02}", i)).collect();
+    let title = "An article with a long title, which is not part of the content";
+    let articles: Vec<_> = urls
+        .iter()
+        .map(|url| (Namespace::Articles, url.as_str(), title))
+        .collect();
+    let raw = write_articles(&articles);
+    let len = raw.len() as u64;
+    let zim = Zim::new_from_reader(Cursor::new(raw)).unwrap();
+    assert!(zim.url_list[urls.len() - 1] > len / 2);
+
+    let iterated: Vec<String> = zim.iterate_by_urls().map(|entry| entry.url).collect();
+    assert_eq!(iterated, urls);
+    assert_eq!(zim.iterate_by_urls().next_back().unwrap().url, urls[19]);
+}
+
+#[test]
+fn test_invalid_entry_index() {
+    let zim = zim_with_articles(&[(Namespace::Articles, "Apple", "")]);
+
+    for result in &[zim.get_by_url_index(1), zim.get_by_title_index(7)] {
+        match result {
+            Err(Error::InvalidEntryIndex { max: 1, .. }) => {}
+            _ => panic!("expected an invalid entry index error"),
+        }
+    }
+}
+
+#[test]
+fn test_get_blob_for_entry() {
+    let zim = open_written(writer_with_articles(
+        &[(Namespace::Articles, "Apple", "")],
+        &[(Namespace::Articles, "Fruit", "Apple")],
+    ));
+
+    let apple = zim
+        .get_by_url(Namespace::Articles, "Apple")
+        .unwrap()
+        .unwrap();
+    assert_eq!(&zim.get_blob_for_entry(&apple).unwrap()[..], b"Apple");
+
+    let fruit = zim
+        .get_by_url(Namespace::Articles, "Fruit")
+        .unwrap()
+        .unwrap();
+    match zim.get_blob_for_entry(&fruit) {
+        Err(Error::IsRedirect) => {}
+        _ => panic!("expected a redirect error"),
+    }
+
+    let deleted = DirectoryEntry {
+        mime_type: MimeType::DeletedEntry,
+        target: None,
+        ..fruit
+    };
+    assert!(matches!(
+        zim.get_blob_for_entry(&deleted),
+        Err(Error::NoTarget)
+    ));
+
+    let dangling = DirectoryEntry {
+        mime_type: MimeType::Type("text/html".into()),
+        target: Some(Target::Cluster(99, 0)),
+        ..deleted
+    };
+    assert!(matches!(
+        zim.get_blob_for_entry(&dangling),
+        Err(Error::InvalidClusterIndex { idx: 99, max: 1 })
+    ));
+}
+
+#[test]
+fn test_get_cluster_corrupt_pointers() {
+    let mut zim = zim_with_articles(&[(Namespace::Articles, "Apple", "")]);
+
+    assert!(zim.get_cluster(0).is_ok());
+    assert!(matches!(
+        zim.get_cluster(1),
+        Err(Error::InvalidClusterIndex { idx: 1, max: 1 })
+    ));
+
+    // the cluster would end where it starts
+    zim.cluster_list[0] = zim.header.checksum_pos;
+    assert!(matches!(
+        zim.get_cluster(0),
+        Err(Error::CorruptCluster { cluster_idx: 0, .. })
+    ));
+}
+
+#[test]
+fn test_sizes() {
+    use crate::writer::ZimWriterBuilder;
+
+    let mut writer = ZimWriterBuilder::new().cluster_size(4).build();
+    for url in &["Apple", "Banana"] {
+        writer
+            .add_article(Namespace::Articles, url, "", "text/html", url.as_bytes())
+            .unwrap();
+    }
+    let mut raw = Vec::new();
+    writer.finalize(&mut raw).unwrap();
+    let len = raw.len() as u64;
+    let zim = Zim::new_from_reader(Cursor::new(raw)).unwrap();
+
+    assert_eq!(zim.file_size(), len);
+    let clusters: u64 = zim
+        .iterate_clusters()
+        .map(|(_, cluster)| cluster.unwrap().compressed_size() + 1)
+        .sum();
+    assert_eq!(zim.total_compressed_size(), clusters);
+    assert_eq!(zim.metadata_overhead_bytes(), len - clusters - 16);
+    assert!(zim.metadata_overhead_bytes() >= 80);
+}
+
+#[test]
+fn test_get_n_entries() {
+    let zim = zim_with_articles(&[
+        (Namespace::Articles, "Apple", ""),
+        (Namespace::Articles, "Banana", ""),
+        (Namespace::Articles, "Cherry", ""),
+    ]);
+
+    let entries = zim.get_n_entries(&[2, 0, 5, 2]);
+    assert_eq!(entries.len(), 4);
+    assert_eq!(entries[0].as_ref().unwrap().url, "Cherry");
+    assert_eq!(entries[1].as_ref().unwrap().url, "Apple");
+    assert!(matches!(
+        entries[2],
+        Err(Error::InvalidEntryIndex { idx: 5, .. })
+    ));
+    assert_eq!(entries[3].as_ref().unwrap().url, "Cherry");
+}
+
+#[test]
+fn test_index_lookups() {
+    let zim = open_written(writer_with_articles(
+        &[
+            (Namespace::Articles, "Banana", "Yellow fruit"),
+            (Namespace::Articles, "Apple", ""),
+            (Namespace::ImagesFile, "Apple", "Apple image"),
+        ],
+        &[(Namespace::Articles, "Cherry", "Apple")],
+    ));
+
+    for &(namespace, url) in &[
+        (Namespace::Articles, "Apple"),
+        (Namespace::Articles, "Banana"),
+        (Namespace::Articles, "Cherry"),
+        (Namespace::ImagesFile, "Apple"),
+    ] {
+        let idx = zim.article_index_for_url(url, namespace).unwrap().unwrap();
+        let entry = zim.get_by_url_index(idx).unwrap();
+        assert_eq!((entry.namespace, entry.url.as_str()), (namespace, url));
+    }
+    assert_eq!(
+        zim.article_index_for_url("Durian", Namespace::Articles)
+            .unwrap(),
+        None
+    );
+
+    for &(namespace, title, url) in &[
+        (Namespace::Articles, "Apple", "Apple"),
+        (Namespace::Articles, "Yellow fruit", "Banana"),
+        (Namespace::Articles, "Cherry", "Cherry"),
+        (Namespace::ImagesFile, "Apple image", "Apple"),
+    ] {
+        let idx = zim
+            .title_index_for_title(title, namespace)
+            .unwrap()
+            .unwrap();
+        let entry = zim.get_by_title_index(idx).unwrap();
+        assert_eq!((entry.namespace, entry.url.as_str()), (namespace, url));
+    }
+    assert_eq!(
+        zim.title_index_for_title("Banana", Namespace::Articles)
+            .unwrap(),
+        None
+    );
 }
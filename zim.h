@@ -0,0 +1,85 @@
+#ifndef ZIM_H
+#define ZIM_H
+
+/* This file is generated by cbindgen, do not edit it manually. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * The call succeeded.
+ */
+#define ZIM_OK 0
+
+/**
+ * A pointer argument is null, an index is out of range or the file could not be read.
+ */
+#define ZIM_ERROR -1
+
+/**
+ * The output buffer is too small for the result, including the terminating null byte.
+ */
+#define ZIM_BUFFER_TOO_SMALL -2
+
+/**
+ * An open ZIM file.
+ */
+typedef struct ZimHandle ZimHandle;
+
+/**
+ * Opens the ZIM file at `path`, returning null on failure.
+ *
+ * # Safety
+ *
+ * `path` must be null or point to a null terminated string.
+ */
+struct ZimHandle *zim_open(const char *path);
+
+/**
+ * Closes a handle returned by `zim_open`. All blobs returned for it become invalid.
+ *
+ * # Safety
+ *
+ * `h` must be null or a handle returned by `zim_open` which has not been closed yet.
+ */
+void zim_close(struct ZimHandle *h);
+
+/**
+ * Returns the number of entries, or 0 if `h` is null.
+ *
+ * # Safety
+ *
+ * `h` must be null or a valid handle.
+ */
+uint32_t zim_get_article_count(const struct ZimHandle *h);
+
+/**
+ * Copies the URL of the entry at URL index `idx` into `out`, followed by a null byte.
+ *
+ * Returns `ZIM_BUFFER_TOO_SMALL` if the URL and the null byte do not fit into `len` bytes.
+ *
+ * # Safety
+ *
+ * `h` must be null or a valid handle, and `out` must be null or point to `len` writable bytes.
+ */
+int zim_get_entry_url(const struct ZimHandle *h, uint32_t idx, char *out, size_t len);
+
+/**
+ * Sets `out` and `len` to the content of a blob.
+ *
+ * The blob is owned by the handle and stays valid until `zim_close`.
+ *
+ * # Safety
+ *
+ * `h` must be null or a valid handle, and `out` and `len` must be null or writable.
+ */
+int zim_get_blob(const struct ZimHandle *h,
+                 uint32_t cluster_idx,
+                 uint32_t blob_idx,
+                 const uint8_t **out,
+                 size_t *len);
+
+#endif  /* ZIM_H */